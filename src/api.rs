@@ -2,13 +2,17 @@
 extern crate pub_sub;
 
 use exonum::{
-    api::{self, ServiceApiBuilder, ServiceApiState}, blockchain::{Transaction, Schema},
-    crypto::{Hash, PublicKey}, node::TransactionSend, helpers::Height,
+    api::{self, ServiceApiBuilder, ServiceApiState}, blockchain::{BlockProof, Transaction, Schema},
+    crypto::{Hash, PublicKey}, node::TransactionSend, helpers::Height, storage::{ListProof, MapProof},
 };
 
 use tx::AuctionTransactions;
-use schema::{Bid, Wallet};
+use schema::{
+    Asset, AssetLedger, AuctionFormat, Bid, BundleBid, Currency, Dispute, DisputeStep, Escrow, Extension, LotStatus, Order,
+    OrderSide, Trade, Wallet, ASSET_LEDGERS_TABLE_INDEX, DISPUTES_TABLE_INDEX,
+};
 use Schema as AuctionSchema;
+use SERVICE_ID;
 
 static mut BLOCK_PS: Option<pub_sub::PubSub<Height>> = None;
 
@@ -26,6 +30,43 @@ pub struct BidHistoryQuery {
     pub id: Hash,
 }
 
+/// Describes the query parameters for the `lot_price` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct LotQuery {
+    /// Hash describing the lot to be queried.
+    pub id: Hash,
+}
+
+/// Describes the query parameters for the `asset` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct AssetQuery {
+    /// Hash describing the asset to be queried.
+    pub id: Hash,
+}
+
+/// Describes the query parameters for the `market` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct MarketQuery {
+    /// Hash of the fungible asset.
+    pub asset: Hash,
+}
+
+/// Describes the query parameters for the `market_proof` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct MarketProofQuery {
+    /// Hash of the fungible asset.
+    pub asset: Hash,
+    /// Public key of the wallet whose holdings are proven.
+    pub owner: PublicKey,
+}
+
+/// Describes the query parameters for the `supply` endpoint.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct CurrencyQuery {
+    /// Identifier of the currency, `schema::native_currency()` for the native one.
+    pub currency: Hash,
+}
+
 /// Asynchronous response to an incoming transaction returned by the REST API.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TransactionResponse {
@@ -54,8 +95,90 @@ pub struct TransactionSyncResponse {
 /// Bid history information.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BidHistory {
+    /// Current status of the lot, `None` if the lot doesn't exist.
+    pub status: Option<LotStatus>,
     /// List of outstanding bids.
     pub bids: Vec<Bid>,
+    /// Deadline extensions caused by bids placed near the end of the auction.
+    pub extensions: Vec<Extension>,
+    /// Pending bundle bids including the lot.
+    pub bundles: Vec<BundleBid>,
+    /// Payment held in escrow until the buyer confirms delivery, `None` if the lot isn't in escrow.
+    pub escrow: Option<Escrow>,
+}
+
+/// Current price of a Dutch auction lot.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LotPrice {
+    /// Height of the next block, the price is valid for transactions included in it.
+    pub height: Height,
+    /// Price at which the lot can be bought.
+    pub price: u64,
+}
+
+/// Total supply of a currency.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Supply {
+    /// Amount of funds in the currency ever issued to wallets.
+    pub total: u64,
+}
+
+/// Funds collected by the treasury.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Treasury {
+    /// Key of the treasury wallet set in the service configuration.
+    pub pub_key: Option<PublicKey>,
+    /// Total amount of each currency ever paid to the treasury.
+    pub totals: Vec<TreasuryTotal>,
+}
+
+/// Total amount of a currency paid to the treasury.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TreasuryTotal {
+    /// Identifier of the currency.
+    pub currency: Hash,
+    /// Listing fees and commissions paid in the currency.
+    pub total: u64,
+}
+
+/// Steps of a dispute with the proof of their inclusion into the latest committed block.
+#[derive(Debug, Serialize)]
+pub struct DisputeProof {
+    /// Latest committed block with its precommits.
+    pub block_proof: BlockProof,
+    /// Proof of the disputes table root in the block's state hash.
+    pub to_table: MapProof<Hash, Hash>,
+    /// Proof of the dispute summary in the disputes table.
+    pub to_dispute: MapProof<Hash, Dispute>,
+    /// Proof of all dispute steps against the summary's `history_hash`.
+    pub history: ListProof<DisputeStep>,
+}
+
+/// Order book and trades of a fungible asset.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Market {
+    /// Buy orders, best price first.
+    pub bids: Vec<Order>,
+    /// Sell orders, best price first.
+    pub asks: Vec<Order>,
+    /// Trades in the order of execution.
+    pub trades: Vec<Trade>,
+}
+
+/// Holdings of a wallet and trades of a fungible asset with the proof of their inclusion into the latest
+/// committed block.
+#[derive(Debug, Serialize)]
+pub struct MarketProof {
+    /// Latest committed block with its precommits.
+    pub block_proof: BlockProof,
+    /// Proof of the asset ledgers table root in the block's state hash.
+    pub to_table: MapProof<Hash, Hash>,
+    /// Proof of the asset's ledger summary in the asset ledgers table.
+    pub to_ledger: MapProof<Hash, AssetLedger>,
+    /// Proof of the wallet's units against the summary's `holdings_hash`.
+    pub holdings: MapProof<PublicKey, u64>,
+    /// Proof of all trades against the summary's `trades_hash`, `None` if no trades have been executed.
+    pub trades: Option<ListProof<Trade>>,
 }
 
 /// Public service API description.
@@ -70,13 +193,125 @@ impl PublicApi {
         schema.wallet(&query.pub_key).ok_or_else(|| api::Error::NotFound("\"Wallet not found\"".to_owned()))
     }
 
+    /// Endpoint for getting the total supply of a currency
+    pub fn supply(state: &ServiceApiState, query: CurrencyQuery) -> api::Result<Supply> {
+        let snapshot = state.snapshot();
+        let schema = AuctionSchema::new(&snapshot);
+
+        if !schema.currency_exists(&query.currency) {
+            return Err(api::Error::NotFound("\"Currency not found\"".to_owned()));
+        }
+
+        Ok(Supply { total: schema.total_issuance(&query.currency) })
+    }
+
+    /// Endpoint for getting the funds collected by the treasury
+    pub fn treasury(state: &ServiceApiState, _query: ()) -> api::Result<Treasury> {
+        let snapshot = state.snapshot();
+        let schema = AuctionSchema::new(&snapshot);
+        let totals = schema
+            .treasury()
+            .iter()
+            .map(|(currency, total)| TreasuryTotal { currency, total })
+            .collect();
+        Ok(Treasury { pub_key: schema.config().treasury, totals })
+    }
+
+    /// Endpoint for listing registered currencies
+    pub fn currencies(state: &ServiceApiState, _query: ()) -> api::Result<Vec<Currency>> {
+        let snapshot = state.snapshot();
+        let schema = AuctionSchema::new(&snapshot);
+        Ok(schema.currencies().values().collect())
+    }
+
+    /// Endpoint for getting a single unique asset
+    pub fn asset(state: &ServiceApiState, query: AssetQuery) -> api::Result<Asset> {
+        let snapshot = state.snapshot();
+        let schema = AuctionSchema::new(&snapshot);
+        schema.asset(&query.id).ok_or_else(|| api::Error::NotFound("\"Asset not found\"".to_owned()))
+    }
+
     /// Endpoint for retrieving full bid history for a single lot
     pub fn bid_history(state: &ServiceApiState, query: BidHistoryQuery) -> api::Result<BidHistory> {
         let snapshot = state.snapshot();
         let schema = AuctionSchema::new(&snapshot);
-        let history = schema.bid_history(&query.id);
-        let bids = history.iter().collect::<Vec<_>>();
-        Ok(BidHistory { bids })
+        let bids = schema.bid_history(&query.id).iter().collect::<Vec<_>>();
+        let extensions = schema.extensions(&query.id).iter().collect::<Vec<_>>();
+        let lot = schema.lot(&query.id);
+        let status = lot.as_ref().and_then(|lot| LotStatus::from_raw(lot.status()));
+        let bundles = lot.map_or_else(Vec::new, |lot| schema.lot_bundle_bids(&lot));
+        let escrow = schema.escrow(&query.id);
+        Ok(BidHistory { status, bids, extensions, bundles, escrow })
+    }
+
+    /// Endpoint for getting the current price of a Dutch auction lot
+    pub fn lot_price(state: &ServiceApiState, query: LotQuery) -> api::Result<LotPrice> {
+        let snapshot = state.snapshot();
+        let schema = AuctionSchema::new(&snapshot);
+        let lot = schema.lot(&query.id).ok_or_else(|| api::Error::NotFound("\"Lot not found\"".to_owned()))?;
+
+        if !lot.has_format(AuctionFormat::Dutch) {
+            return Err(api::Error::BadRequest("\"Lot is not a Dutch auction\"".to_owned()));
+        }
+
+        let height = schema.height();
+        Ok(LotPrice { height, price: lot.current_price(height) })
+    }
+
+    /// Endpoint for retrieving the steps of a lot's dispute with their proof
+    pub fn dispute(state: &ServiceApiState, query: LotQuery) -> api::Result<DisputeProof> {
+        let snapshot = state.snapshot();
+        let general_schema = Schema::new(&snapshot);
+        let schema = AuctionSchema::new(&snapshot);
+
+        let history = schema.dispute_history(&query.id);
+        if history.len() == 0 {
+            return Err(api::Error::NotFound("\"Dispute not found\"".to_owned()));
+        }
+
+        let block_proof = general_schema
+            .block_and_precommits(general_schema.height())
+            .ok_or_else(|| api::Error::NotFound("\"Block not found\"".to_owned()))?;
+        let to_table = general_schema.get_proof_to_service_table(SERVICE_ID, DISPUTES_TABLE_INDEX);
+        let to_dispute = schema.disputes().get_proof(query.id);
+        let history = history.get_range_proof(0, history.len());
+        Ok(DisputeProof { block_proof, to_table, to_dispute, history })
+    }
+
+    /// Endpoint for retrieving the order book and trades of a fungible asset
+    pub fn market(state: &ServiceApiState, query: MarketQuery) -> api::Result<Market> {
+        let snapshot = state.snapshot();
+        let schema = AuctionSchema::new(&snapshot);
+
+        if schema.fungible_asset(&query.asset).is_none() {
+            return Err(api::Error::NotFound("\"Asset not found\"".to_owned()));
+        }
+
+        let bids = schema.book_orders(&query.asset, OrderSide::Buy);
+        let asks = schema.book_orders(&query.asset, OrderSide::Sell);
+        let trades = schema.trades(&query.asset).iter().collect::<Vec<_>>();
+        Ok(Market { bids, asks, trades })
+    }
+
+    /// Endpoint for retrieving a wallet's holdings and the trades of a fungible asset with their proof
+    pub fn market_proof(state: &ServiceApiState, query: MarketProofQuery) -> api::Result<MarketProof> {
+        let snapshot = state.snapshot();
+        let general_schema = Schema::new(&snapshot);
+        let schema = AuctionSchema::new(&snapshot);
+
+        if schema.fungible_asset(&query.asset).is_none() {
+            return Err(api::Error::NotFound("\"Asset not found\"".to_owned()));
+        }
+
+        let block_proof = general_schema
+            .block_and_precommits(general_schema.height())
+            .ok_or_else(|| api::Error::NotFound("\"Block not found\"".to_owned()))?;
+        let to_table = general_schema.get_proof_to_service_table(SERVICE_ID, ASSET_LEDGERS_TABLE_INDEX);
+        let to_ledger = schema.asset_ledgers().get_proof(query.asset);
+        let holdings = schema.asset_holdings(&query.asset).get_proof(query.owner);
+        let trades = schema.trades(&query.asset);
+        let trades = if trades.len() > 0 { Some(trades.get_range_proof(0, trades.len())) } else { None };
+        Ok(MarketProof { block_proof, to_table, to_ledger, holdings, trades })
     }
 
     /// Endpoint for handling asynchronous transactions.
@@ -138,7 +373,34 @@ impl PublicApi {
             .endpoint("v1/wallet", Self::wallet)
             .endpoint("v1/bids", Self::bid_history)
             .endpoint_mut("v1/bids", Self::post_transaction_sync)
+            .endpoint_mut("v1/bids/proxy", Self::post_transaction)
+            .endpoint_mut("v1/bids/bundle", Self::post_transaction)
+            .endpoint_mut("v1/bids/retract", Self::post_transaction)
+            .endpoint_mut("v1/bids/commit", Self::post_transaction)
+            .endpoint_mut("v1/bids/reveal", Self::post_transaction)
+            .endpoint("v1/lots/price", Self::lot_price)
             .endpoint_mut("v1/lots", Self::post_transaction)
-            .endpoint_mut("v1/wallets", Self::post_transaction);
+            .endpoint_mut("v1/lots/buy", Self::post_transaction)
+            .endpoint_mut("v1/lots/accept", Self::post_transaction)
+            .endpoint_mut("v1/lots/close", Self::post_transaction)
+            .endpoint_mut("v1/lots/cancel", Self::post_transaction)
+            .endpoint_mut("v1/lots/confirm", Self::post_transaction)
+            .endpoint("v1/disputes", Self::dispute)
+            .endpoint_mut("v1/disputes", Self::post_transaction)
+            .endpoint_mut("v1/disputes/resolve", Self::post_transaction)
+            .endpoint_mut("v1/wallets", Self::post_transaction)
+            .endpoint_mut("v1/wallets/transfer", Self::post_transaction)
+            .endpoint_mut("v1/wallets/issue", Self::post_transaction)
+            .endpoint("v1/supply", Self::supply)
+            .endpoint("v1/treasury", Self::treasury)
+            .endpoint("v1/currencies", Self::currencies)
+            .endpoint_mut("v1/currencies", Self::post_transaction)
+            .endpoint("v1/assets", Self::asset)
+            .endpoint_mut("v1/assets", Self::post_transaction)
+            .endpoint("v1/market", Self::market)
+            .endpoint("v1/market/proof", Self::market_proof)
+            .endpoint_mut("v1/market/assets", Self::post_transaction)
+            .endpoint_mut("v1/market/orders", Self::post_transaction)
+            .endpoint_mut("v1/market/orders/cancel", Self::post_transaction);
     }
 }
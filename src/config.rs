@@ -0,0 +1,56 @@
+//! Service configuration
+
+use exonum::crypto::PublicKey;
+use serde::{de::Error, Deserialize, Deserializer};
+
+/// Maximum value of `AuctionConfig::commission_percent`
+pub const MAX_COMMISSION_PERCENT: u64 = 100;
+
+/// Maximum value of `AuctionConfig::escrow_period`
+pub const MAX_ESCROW_PERIOD: u64 = 1_000_000;
+
+/// Global configuration of the auction service, stored in the blockchain configuration
+/// under the service name. The initial configuration is read from the `auction` section
+/// of the node's `services_configs` and can be changed later by the configuration service.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuctionConfig {
+    /// Keys allowed to issue the native currency and to register other currencies.
+    #[serde(default)]
+    pub issuers: Vec<PublicKey>,
+    /// Amount of blocks the payment for a sold lot is held in escrow before it is released
+    /// to the seller, 0 pays sellers right away, at most `MAX_ESCROW_PERIOD`.
+    #[serde(default, deserialize_with = "deserialize_escrow_period")]
+    pub escrow_period: u64,
+    /// Keys allowed to resolve disputes over lots in escrow with `ResolveDispute` transactions.
+    #[serde(default)]
+    pub arbiters: Vec<PublicKey>,
+    /// Key of the wallet collecting listing fees and commissions, no fees are charged if not set.
+    #[serde(default)]
+    pub treasury: Option<PublicKey>,
+    /// Amount of the native currency paid to the treasury for creating a lot.
+    #[serde(default)]
+    pub listing_fee: u64,
+    /// Percentage of the price of a sold lot paid to the treasury instead of the seller,
+    /// at most `MAX_COMMISSION_PERCENT`.
+    #[serde(default, deserialize_with = "deserialize_commission_percent")]
+    pub commission_percent: u64,
+}
+
+/// Rejects configurations charging a commission above the price of a lot
+fn deserialize_commission_percent<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    deserialize_bounded(deserializer, "commission_percent", MAX_COMMISSION_PERCENT)
+}
+
+/// Rejects configurations holding payments in escrow for too long
+fn deserialize_escrow_period<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    deserialize_bounded(deserializer, "escrow_period", MAX_ESCROW_PERIOD)
+}
+
+/// Deserializes a number, failing if it exceeds the given maximum
+fn deserialize_bounded<'de, D: Deserializer<'de>>(deserializer: D, name: &str, max: u64) -> Result<u64, D::Error> {
+    let value = u64::deserialize(deserializer)?;
+    if value > max {
+        return Err(D::Error::custom(format!("{} can't exceed {}", name, max)));
+    }
+    Ok(value)
+}
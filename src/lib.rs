@@ -1,25 +1,31 @@
 //! A simple auction service implementation example using [exonum](http://exonum.com/).
 #![deny(missing_debug_implementations, missing_docs, bare_trait_objects)]
 
+extern crate chrono;
 #[macro_use]
 extern crate exonum;
+extern crate exonum_time;
 #[macro_use]
 extern crate failure;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 
+pub use config::AuctionConfig;
 pub use schema::Schema;
 
 pub mod api;
+pub mod config;
 pub mod schema;
 pub mod tx;
 
 use exonum::{
     api::ServiceApiBuilder, blockchain::{self, Transaction, TransactionSet, ServiceContext}, crypto::Hash,
-    encoding::Error as EncodingError, helpers::fabric::{self, Context}, messages::RawTransaction,
-    storage::Snapshot,
+    encoding::Error as EncodingError, helpers::fabric::{self, keys, Context}, messages::RawTransaction,
+    storage::{Fork, Snapshot},
 };
+use serde_json::Value;
 
 use tx::AuctionTransactions;
 
@@ -29,7 +35,30 @@ pub const SERVICE_NAME: &str = "auction";
 
 /// Service implementation
 #[derive(Default, Debug)]
-pub struct Service;
+pub struct Service {
+    config: AuctionConfig,
+}
+
+impl Service {
+    /// Creates the service with the given initial configuration
+    ///
+    /// # Panics
+    /// If `config.commission_percent` exceeds `config::MAX_COMMISSION_PERCENT`
+    /// or `config.escrow_period` exceeds `config::MAX_ESCROW_PERIOD`.
+    pub fn new(config: AuctionConfig) -> Self {
+        assert!(
+            config.commission_percent <= config::MAX_COMMISSION_PERCENT,
+            "Invalid auction service configuration: commission_percent can't exceed {}",
+            config::MAX_COMMISSION_PERCENT
+        );
+        assert!(
+            config.escrow_period <= config::MAX_ESCROW_PERIOD,
+            "Invalid auction service configuration: escrow_period can't exceed {}",
+            config::MAX_ESCROW_PERIOD
+        );
+        Service { config }
+    }
+}
 
 impl blockchain::Service for Service {
     fn service_id(&self) -> u16 {
@@ -49,6 +78,16 @@ impl blockchain::Service for Service {
         AuctionTransactions::tx_from_raw(raw).map(Into::into)
     }
 
+    fn initialize(&self, _fork: &mut Fork) -> Value {
+        serde_json::to_value(&self.config).expect("Could not serialize the service configuration")
+    }
+
+    fn before_commit(&self, fork: &mut Fork) {
+        let mut schema = Schema::new(fork);
+        schema.close_expired_lots();
+        schema.release_expired_escrows();
+    }
+
     fn after_commit(&self, context: &ServiceContext) {
         unsafe { api::PublicApi::sync_commit_callback(context.height()); }
     }
@@ -67,7 +106,13 @@ impl fabric::ServiceFactory for ServiceFactory {
         SERVICE_NAME
     }
 
-    fn make_service(&mut self, _: &Context) -> Box<dyn blockchain::Service> {
-        Box::new(Service)
+    fn make_service(&mut self, run_context: &Context) -> Box<dyn blockchain::Service> {
+        let config = run_context
+            .get(keys::NODE_CONFIG)
+            .ok()
+            .and_then(|node_config| node_config.services_configs.get(SERVICE_NAME).cloned())
+            .map(|value| value.try_into().expect("Invalid auction service configuration"))
+            .unwrap_or_default();
+        Box::new(Service::new(config))
     }
 }
@@ -1,9 +1,11 @@
 extern crate exonum;
 extern crate exonum_configuration;
+extern crate exonum_time;
 extern crate exonum_auction;
 
 use exonum::helpers::{self, fabric::NodeBuilder};
 use exonum_configuration as configuration;
+use exonum_time::TimeServiceFactory;
 
 fn main() {
     exonum::crypto::init();
@@ -11,6 +13,7 @@ fn main() {
 
     let node = NodeBuilder::new()
         .with_service(Box::new(configuration::ServiceFactory))
+        .with_service(Box::new(TimeServiceFactory))
         .with_service(Box::new(exonum_auction::ServiceFactory));
     node.run();
 }
@@ -1,12 +1,31 @@
 //! Database schema
 
+use std::cmp::{max, min};
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use exonum::{
-    crypto::{Hash, PublicKey}, storage::{Fork, ProofListIndex, ProofMapIndex, Snapshot},
-    blockchain::ExecutionError,
+    crypto::{Hash, HashStream, PublicKey},
+    storage::{Entry, Fork, KeySetIndex, ListIndex, MapIndex, ProofListIndex, ProofMapIndex, Snapshot},
+    blockchain::{self, ExecutionError}, helpers::Height,
 };
+use exonum_time::schema::TimeSchema;
+use serde_json;
 
+use config::AuctionConfig;
 use SERVICE_NAME;
 
+encoding_struct! {
+    /// Funds of a wallet in a single currency.
+    struct Balance {
+        /// Identifier of the currency, see `Currency`.
+        currency: &Hash,
+        /// Funds available for bidding and payments.
+        available: u64,
+        /// Funds frozen due to active bids, not included in `available`.
+        frozen:    u64,
+    }
+}
+
 encoding_struct! {
     /// Wallet information stored in the database.
     struct Wallet {
@@ -14,10 +33,36 @@ encoding_struct! {
         pub_key: &PublicKey,
         /// Name of the wallet.
         name:    &str,
-        /// Current balance of the wallet.
-        balance: u64,
-        /// Amount frozen due to active bids
-        frozen:  u64,
+        /// Funds held in each currency, currencies the wallet has never held are omitted.
+        balances: Vec<Balance>,
+    }
+}
+
+encoding_struct! {
+    /// Currency registered in the service
+    struct Currency {
+        /// Hash of the transaction that registered the currency, used as its identifier
+        id: &Hash,
+        /// Currency name
+        name: &str,
+        /// Amount of decimal places used to display amounts, which are stored in the smallest units
+        decimals: u8,
+        /// `PublicKey` of the only participant allowed to issue the currency
+        issuer: &PublicKey,
+    }
+}
+
+encoding_struct! {
+    /// Unique item which can be sold in a lot
+    struct Asset {
+        /// Hash of the transaction that issued the asset, used as its identifier
+        id: &Hash,
+        /// Asset name
+        name: &str,
+        /// `PublicKey` of the current owner
+        owner: &PublicKey,
+        /// Hash of the open lot the asset is sold in, `Hash::zero()` if the asset isn't locked
+        lot: &Hash,
     }
 }
 
@@ -28,10 +73,68 @@ encoding_struct! {
         owner: &PublicKey,
         /// Lot name
         name: &str,
-        /// Minimum starting bid
+        /// Minimum starting bid, the owner's budget frozen for reverse auction lots
         min_bid: u64,
         /// Hash of the transaction that created this lot
         tx_hash: &Hash,
+        /// Current status of the lot, see `LotStatus`
+        status: u8,
+        /// Height of the last block accepting bids, the lot is closed automatically
+        /// once this block is committed (0 if the lot has no deadline)
+        end_height: u64,
+        /// Consensus time after which no more bids are accepted
+        /// (`no_time_deadline()` if the lot has no wall-clock deadline)
+        closes_at: DateTime<Utc>,
+        /// Bids placed this close to a deadline extend it (in blocks for `end_height`,
+        /// in seconds for `closes_at`, 0 to disable extensions)
+        extension_window: u64,
+        /// Amount of blocks or seconds a deadline is moved forward by
+        extension: u64,
+        /// Commitment to the hidden reserve price, see `amount_commitment`
+        /// (`Hash::zero()` if the lot has no reserve)
+        reserve_hash: &Hash,
+        /// Price at which the lot is sold immediately (0 if not available)
+        buy_now: u64,
+        /// Minimum amount by which a bid must exceed the current highest one
+        min_increment: u64,
+        /// Minimum increment as a percentage of the current highest bid
+        min_increment_percent: u64,
+        /// Auction format of the lot, see `AuctionFormat`
+        format: u8,
+        /// Amount of blocks after `end_height` during which sealed bids are revealed
+        /// (0 for open auctions)
+        reveal_period: u64,
+        /// Price of a Dutch auction lot at `start_height`, `min_bid` is used as the floor price
+        start_price: u64,
+        /// Amount the price of a Dutch auction lot decreases by with every block
+        price_decrement: u64,
+        /// Height of the block the lot has been created in
+        start_height: u64,
+        /// Amount of identical units sold in the lot (0 or 1 for a single item)
+        quantity: u64,
+        /// Amount of blocks after placing a bid during which the bidder can retract it
+        /// while leading (0 if bids can't be retracted)
+        retraction_window: u64,
+        /// Amount the bidder retracting a bid pays to the lot owner
+        retraction_penalty: u64,
+        /// Identifier of the currency all amounts of the lot are in, see `Currency`
+        currency: &Hash,
+        /// Identifier of the asset sold in the lot, `Hash::zero()` for lots without an asset
+        asset: &Hash,
+    }
+}
+
+encoding_struct! {
+    /// Deadline extension caused by a bid placed near the end of an auction
+    struct Extension {
+        /// `PublicKey` of the bidder who triggered the extension
+        bidder: &PublicKey,
+        /// Amount of the triggering bid
+        amount: u64,
+        /// Lot's effective height deadline after the extension
+        end_height: u64,
+        /// Lot's effective wall-clock deadline after the extension
+        closes_at: DateTime<Utc>,
     }
 }
 
@@ -40,164 +143,2631 @@ encoding_struct! {
     struct Bid {
         /// `PublicKey` of a wallet owner placing the bid
         owner: &PublicKey,
-        /// Bid amount
+        /// Bid amount, per unit for multi-unit lots
         amount: u64,
         /// Hash of the transaction that created this bid
         tx_hash: &Hash,
+        /// Amount of units bid for
+        quantity: u64,
+        /// Height of the block the bid has been placed in
+        height: u64,
+        /// Whether the bid has been retracted by its owner, see `Schema::retract_bid`
+        retracted: bool,
+        /// Whether the bid has lapsed because its owner could no longer fund it
+        /// when a later bid was retracted, see `Schema::retract_bid`
+        lapsed: bool,
+    }
+}
+
+encoding_struct! {
+    /// A sealed bid committed to during the commit phase of a sealed-bid auction
+    struct SealedBid {
+        /// `PublicKey` of the bidder
+        owner: &PublicKey,
+        /// Commitment to the bid amount, see `amount_commitment`
+        commitment: &Hash,
+        /// Amount frozen until the lot is settled, the revealed bid can't exceed it
+        deposit: u64,
+        /// Revealed bid amount (0 until revealed)
+        amount: u64,
+        /// Whether the bid has been revealed
+        revealed: bool,
+    }
+}
+
+encoding_struct! {
+    /// Maximum amount the chain bids on behalf of the leading bidder
+    struct ProxyBid {
+        /// `PublicKey` of the bidder
+        owner: &PublicKey,
+        /// Maximum bid amount, frozen in the bidder's wallet while the proxy leads
+        max_amount: u64,
+    }
+}
+
+encoding_struct! {
+    /// Payment for a sold lot held in the buyer's frozen funds until delivery is confirmed
+    struct Escrow {
+        /// `PublicKey` of the buyer
+        buyer: &PublicKey,
+        /// Price of the lot, frozen in the buyer's wallet
+        amount: u64,
+        /// Height at which the payment is released to the lot owner automatically
+        release_height: u64,
+    }
+}
+
+encoding_struct! {
+    /// Step of a dispute over a lot in escrow
+    struct DisputeStep {
+        /// `PublicKey` of the participant who took the step, the buyer or an arbiter
+        author: &PublicKey,
+        /// Action taken, see `DisputeAction`
+        action: u8,
+        /// Amount refunded to the buyer, 0 unless the step resolves the dispute
+        buyer_amount: u64,
+        /// Amount paid to the seller, 0 unless the step resolves the dispute
+        seller_amount: u64,
+        /// Explanation given by the author
+        comment: &str,
+        /// Height of the block the step has been taken in
+        height: u64,
+        /// Hash of the transaction that took the step
+        tx_hash: &Hash,
+    }
+}
+
+encoding_struct! {
+    /// Summary of a dispute over a lot, aggregating its steps into the service state
+    struct Dispute {
+        /// Amount of steps taken so far
+        history_len: u64,
+        /// Merkle root of the steps, see `Schema::dispute_history`
+        history_hash: &Hash,
+    }
+}
+
+encoding_struct! {
+    /// All-or-nothing bid for several lots settled at the same height
+    struct BundleBid {
+        /// `PublicKey` of the bidder
+        owner: &PublicKey,
+        /// Hashes of the lots in the bundle
+        lots: &[Hash],
+        /// Amount offered for all lots together, frozen until the lots are settled
+        amount: u64,
+        /// Hash of the transaction that placed the bid
+        tx_hash: &Hash,
+    }
+}
+
+encoding_struct! {
+    /// Fungible asset traded on an order book
+    struct FungibleAsset {
+        /// Hash of the transaction that created the asset, used as its identifier
+        id: &Hash,
+        /// Asset name
+        name: &str,
+        /// `PublicKey` of the wallet the whole supply has been issued to
+        issuer: &PublicKey,
+        /// Total amount of units
+        supply: u64,
+    }
+}
+
+encoding_struct! {
+    /// Order resting in the order book of a fungible asset
+    struct Order {
+        /// `PublicKey` of the order owner
+        owner: &PublicKey,
+        /// Identifier of the traded asset
+        asset: &Hash,
+        /// Side of the order, see `OrderSide`
+        side: u8,
+        /// Limit price per unit in the native currency
+        price: u64,
+        /// Amount of units not filled yet
+        quantity: u64,
+        /// Placement sequence number, orders with equal prices are matched in this order
+        sequence: u64,
+        /// Hash of the transaction that placed the order
+        tx_hash: &Hash,
+    }
+}
+
+encoding_struct! {
+    /// Trade between two matched orders
+    struct Trade {
+        /// `PublicKey` of the buyer
+        buyer: &PublicKey,
+        /// `PublicKey` of the seller
+        seller: &PublicKey,
+        /// Price per unit, the limit price of the order resting in the book
+        price: u64,
+        /// Amount of units traded
+        quantity: u64,
+    }
+}
+
+encoding_struct! {
+    /// Summary of a fungible asset's holdings and trades, aggregating them into the service state
+    struct AssetLedger {
+        /// Merkle root of the holdings, see `Schema::asset_holdings`
+        holdings_hash: &Hash,
+        /// Amount of trades executed so far
+        trades_len: u64,
+        /// Merkle root of the trades, see `Schema::trades`
+        trades_hash: &Hash,
     }
 }
 
 use tx::Error;
 
+/// Status of an auction lot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum LotStatus {
+    /// Lot accepts bids
+    Open = 0,
+    /// Lot has been closed and settled
+    Closed = 1,
+    /// Lot has been cancelled by its owner, all bids have been released
+    Cancelled = 2,
+    /// Lot has been sold, the payment is held in escrow until the buyer confirms delivery
+    Escrow = 3,
+    /// Buyer has disputed the delivery, the payment is held in escrow until an arbiter resolves the dispute
+    Disputed = 4,
+}
+
+impl LotStatus {
+    /// Converts a raw value stored in `Lot::status`
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(LotStatus::Open),
+            1 => Some(LotStatus::Closed),
+            2 => Some(LotStatus::Cancelled),
+            3 => Some(LotStatus::Escrow),
+            4 => Some(LotStatus::Disputed),
+            _ => None,
+        }
+    }
+}
+
+/// Rules by which bids are placed on a lot and the lot is settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum AuctionFormat {
+    /// Open ascending auction, the highest bid wins and pays its amount
+    English = 0,
+    /// Sealed bids are committed to and later revealed, the highest revealed bid
+    /// wins and pays its amount
+    SealedBid = 1,
+    /// Sealed bids are committed to and later revealed, the highest revealed bid wins
+    /// and pays the second-highest revealed amount (`min_bid` if there is no other bid)
+    Vickrey = 2,
+    /// Descending price auction, the first participant accepting the current price wins
+    Dutch = 3,
+    /// Procurement auction, the owner pays the lowest bid out of a budget frozen
+    /// when the lot is created
+    Reverse = 4,
+}
+
+impl AuctionFormat {
+    /// Converts a raw value stored in `Lot::format`
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(AuctionFormat::English),
+            1 => Some(AuctionFormat::SealedBid),
+            2 => Some(AuctionFormat::Vickrey),
+            3 => Some(AuctionFormat::Dutch),
+            4 => Some(AuctionFormat::Reverse),
+            _ => None,
+        }
+    }
+
+    /// Checks whether bids are committed to and revealed instead of being placed openly
+    pub fn is_sealed(self) -> bool {
+        match self {
+            AuctionFormat::SealedBid | AuctionFormat::Vickrey => true,
+            AuctionFormat::English | AuctionFormat::Dutch | AuctionFormat::Reverse => false,
+        }
+    }
+}
+
+/// Side of an order in the order book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum OrderSide {
+    /// Buy order, the price multiplied by the quantity is frozen in the owner's wallet
+    Buy = 0,
+    /// Sell order, the units are taken out of the owner's holdings
+    Sell = 1,
+}
+
+impl OrderSide {
+    /// Converts a raw value stored in `Order::side`
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(OrderSide::Buy),
+            1 => Some(OrderSide::Sell),
+            _ => None,
+        }
+    }
+
+    /// Returns the side orders are matched against
+    pub fn opposite(self) -> Self {
+        match self {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        }
+    }
+}
+
+/// Action taken in a step of a dispute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum DisputeAction {
+    /// Buyer has opened the dispute
+    Opened = 0,
+    /// Arbiter has split the escrowed payment between the buyer and the seller
+    Resolved = 1,
+}
+
+impl DisputeAction {
+    /// Converts a raw value stored in `DisputeStep::action`
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(DisputeAction::Opened),
+            1 => Some(DisputeAction::Resolved),
+            _ => None,
+        }
+    }
+}
+
+/// Position of the `Schema::disputes` root in the service state hash, used to prove
+/// dispute steps against a block
+pub const DISPUTES_TABLE_INDEX: usize = 5;
+
+/// Position of the `Schema::asset_ledgers` root in the service state hash, used to prove
+/// holdings and trades of fungible assets against a block
+pub const ASSET_LEDGERS_TABLE_INDEX: usize = 9;
+
+/// Maximum amount of lots in a single bundle bid
+pub const MAX_BUNDLE_LOTS: usize = 8;
+
+/// Maximum amount of bundle bids settled at the same height. Winning bundles are determined
+/// by an exact search over all combinations of bundle bids, which stays cheap up to this limit.
+pub const MAX_BUNDLE_BIDS: u64 = 16;
+
+/// Maximum extension window and extension of lot deadlines, measured in blocks for height
+/// deadlines and in seconds for time deadlines
+pub const MAX_EXTENSION: u64 = 1_000_000;
+
+/// Maximum reveal period of sealed-bid lots, in blocks
+pub const MAX_REVEAL_PERIOD: u64 = 1_000_000;
+
+/// Amount of blocks after the settlement height of a lot with a reserve price during which
+/// the owner can reveal the reserve with `CloseLot`, the lot is then closed without a sale
+pub const RESERVE_REVEAL_PERIOD: u64 = 10;
+
+/// Identifier of the native currency, which is issued by the issuers from the service
+/// configuration and used for the order books
+pub fn native_currency() -> Hash {
+    Hash::zero()
+}
+
+/// Value of `Lot::closes_at` for lots without a wall-clock deadline
+pub fn no_time_deadline() -> DateTime<Utc> {
+    Utc.timestamp(0, 0)
+}
+
+/// Computes a commitment to a hidden amount, e.g. a lot's reserve price
+///
+/// # Arguments
+/// - `amount`: the amount to commit to
+/// - `salt`: random value preventing the amount from being guessed
+pub fn amount_commitment(amount: u64, salt: &Hash) -> Hash {
+    HashStream::new()
+        .update(&amount.to_le_bytes())
+        .update(salt.as_ref())
+        .hash()
+}
+
 impl Wallet {
-    /// Attempts to freeze a given amount in the wallet's balance
+    /// Returns the wallet's funds in the given currency
+    ///
+    /// # Arguments
+    /// `currency` - identifier of the currency
+    pub fn balance(&self, currency: &Hash) -> Balance {
+        self.balances()
+            .into_iter()
+            .find(|balance| balance.currency() == currency)
+            .unwrap_or_else(|| Balance::new(currency, 0, 0))
+    }
+
+    /// Returns the wallet's available funds in the given currency
+    ///
+    /// # Arguments
+    /// `currency` - identifier of the currency
+    pub fn available(&self, currency: &Hash) -> u64 {
+        self.balance(currency).available()
+    }
+
+    /// Returns the wallet's frozen funds in the given currency
+    ///
+    /// # Arguments
+    /// `currency` - identifier of the currency
+    pub fn frozen(&self, currency: &Hash) -> u64 {
+        self.balance(currency).frozen()
+    }
+
+    /// Moves a given amount from the wallet's available funds to the frozen ones
     /// or returns Error::InsufficientCurrencyAmount
     ///
     /// # Arguments
-    /// `amount` - the amount to freeze (u64)
-    pub fn freeze(self, amount: u64) -> Result<Self, Error> {
-        if self.balance() - self.frozen() >= amount {
-            Ok(Self::new(self.pub_key(), self.name(), self.balance() - amount, self.frozen() + amount))
-        } else {
-            Err(Error::InsufficientCurrencyAmount)?
-        }
+    /// - `currency`: identifier of the currency
+    /// - `amount`: the amount to freeze
+    pub fn freeze(self, currency: &Hash, amount: u64) -> Result<Self, Error> {
+        let balance = self.balance(currency);
+        let available = balance.available().checked_sub(amount).ok_or(Error::InsufficientCurrencyAmount)?;
+        let frozen = balance.frozen().checked_add(amount).ok_or(Error::BalanceOverflow)?;
+        Ok(self.with_funds(currency, available, frozen))
     }
 
-    /// Releases a given amount in the wallet's balance
-    /// If the requested amount is greater than the currently frozen one, everything is released
+    /// Moves a given amount from the wallet's frozen funds back to the available ones
+    /// or returns Error::InsufficientFrozenAmount
     ///
     /// # Arguments
-    /// `amount` - the amount to release (u64)
-    pub fn release(self, amount: u64) -> Self {
-        let actual_amount = if self.frozen() <= amount { self.frozen() } else { amount };
-        Self::new(self.pub_key(), self.name(), self.balance() + actual_amount, self.frozen() - actual_amount)
+    /// - `currency`: identifier of the currency
+    /// - `amount`: the amount to release
+    pub fn release(self, currency: &Hash, amount: u64) -> Result<Self, Error> {
+        let balance = self.balance(currency);
+        let frozen = balance.frozen().checked_sub(amount).ok_or(Error::InsufficientFrozenAmount)?;
+        let available = balance.available().checked_add(amount).ok_or(Error::BalanceOverflow)?;
+        Ok(self.with_funds(currency, available, frozen))
     }
-}
 
-/// DB schema
-#[derive(Debug)]
-pub struct Schema<T> {
-    view: T,
+    /// Withdraws a given amount from the wallet's frozen funds, e.g. to pay for a won lot,
+    /// or returns Error::InsufficientFrozenAmount
+    ///
+    /// # Arguments
+    /// - `currency`: identifier of the currency
+    /// - `amount`: the amount to withdraw
+    pub fn withdraw_frozen(self, currency: &Hash, amount: u64) -> Result<Self, Error> {
+        let balance = self.balance(currency);
+        let frozen = balance.frozen().checked_sub(amount).ok_or(Error::InsufficientFrozenAmount)?;
+        Ok(self.with_funds(currency, balance.available(), frozen))
+    }
+
+    /// Withdraws a given amount from the wallet's available funds
+    /// or returns Error::InsufficientCurrencyAmount
+    ///
+    /// # Arguments
+    /// - `currency`: identifier of the currency
+    /// - `amount`: the amount to withdraw
+    pub fn withdraw(self, currency: &Hash, amount: u64) -> Result<Self, Error> {
+        let balance = self.balance(currency);
+        let available = balance.available().checked_sub(amount).ok_or(Error::InsufficientCurrencyAmount)?;
+        Ok(self.with_funds(currency, available, balance.frozen()))
+    }
+
+    /// Adds a given amount to the wallet's available funds
+    ///
+    /// # Arguments
+    /// - `currency`: identifier of the currency
+    /// - `amount`: the amount to deposit
+    pub fn deposit(self, currency: &Hash, amount: u64) -> Result<Self, Error> {
+        let balance = self.balance(currency);
+        let available = balance.available().checked_add(amount).ok_or(Error::BalanceOverflow)?;
+        Ok(self.with_funds(currency, available, balance.frozen()))
+    }
+
+    fn with_funds(&self, currency: &Hash, available: u64, frozen: u64) -> Self {
+        let balance = Balance::new(currency, available, frozen);
+        let mut balances = self.balances();
+        match balances.iter().position(|val| val.currency() == currency) {
+            Some(index) => balances[index] = balance,
+            None => balances.push(balance),
+        }
+        Self::new(self.pub_key(), self.name(), balances)
+    }
 }
 
-impl<T> AsMut<T> for Schema<T> {
-    fn as_mut(&mut self) -> &mut T {
-        &mut self.view
+impl Asset {
+    /// Checks whether the asset is locked by an open lot
+    pub fn is_locked(&self) -> bool {
+        *self.lot() != Hash::zero()
+    }
+
+    /// Returns a copy of the asset locked by the given lot, `Hash::zero()` unlocks the asset
+    pub fn with_lot(self, lot: &Hash) -> Self {
+        Self::new(self.id(), self.name(), self.owner(), lot)
+    }
+
+    /// Returns an unlocked copy of the asset owned by the given participant
+    pub fn with_owner(self, owner: &PublicKey) -> Self {
+        Self::new(self.id(), self.name(), owner, &Hash::zero())
     }
 }
 
-impl<T> Schema<T>
-    where
-        T: AsRef<dyn Snapshot>,
-{
-    /// Creates a new schema from the database view.
-    pub fn new(view: T) -> Self {
-        Schema { view }
+impl Bid {
+    /// Returns `true` if the bid has neither been retracted nor lapsed
+    pub fn is_active(&self) -> bool {
+        !self.retracted() && !self.lapsed()
     }
 
-    /// Returns `ProofMapIndex` with wallets.
-    pub fn wallets(&self) -> ProofMapIndex<&T, PublicKey, Wallet> {
-        ProofMapIndex::new(format!("{}.wallets", SERVICE_NAME), &self.view)
+    /// Returns a copy of the bid marked as retracted
+    pub fn mark_retracted(self) -> Self {
+        Self::new(
+            self.owner(),
+            self.amount(),
+            self.tx_hash(),
+            self.quantity(),
+            self.height(),
+            true,
+            false,
+        )
     }
 
-    /// Returns a `ProofMapIndex` with lots.
-    pub fn lots(&self) -> ProofMapIndex<&T, Hash, Lot> {
-        ProofMapIndex::new(format!("{}.lots", SERVICE_NAME), &self.view)
+    /// Returns a copy of the bid marked as lapsed
+    pub fn mark_lapsed(self) -> Self {
+        Self::new(
+            self.owner(),
+            self.amount(),
+            self.tx_hash(),
+            self.quantity(),
+            self.height(),
+            false,
+            true,
+        )
     }
+}
 
-    /// Returns bid history for a lot with the given hash.
-    pub fn bid_history(&self, hash: &Hash) -> ProofListIndex<&T, Bid> {
-        ProofListIndex::new_in_family(format!("{}.bid_history", SERVICE_NAME), hash, &self.view)
+impl Order {
+    /// Returns a copy of the order with the given unfilled quantity
+    ///
+    /// # Arguments
+    /// `quantity` - the new quantity
+    pub fn with_quantity(self, quantity: u64) -> Self {
+        Self::new(
+            self.owner(),
+            self.asset(),
+            self.side(),
+            self.price(),
+            quantity,
+            self.sequence(),
+            self.tx_hash(),
+        )
     }
 
-    /// Returns the wallet for the given public key.
-    pub fn wallet(&self, pub_key: &PublicKey) -> Option<Wallet> {
-        self.wallets().get(pub_key)
+    /// Checks whether this is a buy order
+    pub fn is_buy(&self) -> bool {
+        self.side() == OrderSide::Buy as u8
     }
 
-    /// Returns a lot by its hash.
-    pub fn lot(&self, id: &Hash) -> Option<Lot> {
-        self.lots().get(id)
+    /// Returns the key of the order in its order book. Keys are ordered by price
+    /// (highest first for buy orders, lowest first for sell orders) and then by placement.
+    pub fn book_key(&self) -> Vec<u8> {
+        let price = if self.is_buy() { u64::max_value() - self.price() } else { self.price() };
+        let mut key = price.to_be_bytes().to_vec();
+        key.extend_from_slice(&self.sequence().to_be_bytes());
+        key
     }
 
-    /// Returns the last bid in a lot's bid history
-    pub fn last_bid(&self, id: &Hash) -> Option<Bid> {
-        match self.lot(id) {
-            Some(_lot) => self.bid_history(id).last(),
-            None => None,
+    /// Checks whether the order can be matched with the given order from the opposite side
+    pub fn crosses(&self, other: &Order) -> bool {
+        if self.is_buy() {
+            self.price() >= other.price()
+        } else {
+            self.price() <= other.price()
         }
     }
+}
 
-    /// Returns the service state hash
-    pub fn state_hash(&self) -> Vec<Hash> {
-        vec![self.wallets().merkle_root()]
+impl Lot {
+    /// Returns a copy of the lot with the given status
+    ///
+    /// # Arguments
+    /// `status` - the new status
+    pub fn with_status(self, status: LotStatus) -> Self {
+        Self::new(
+            self.owner(),
+            self.name(),
+            self.min_bid(),
+            self.tx_hash(),
+            status as u8,
+            self.end_height(),
+            self.closes_at(),
+            self.extension_window(),
+            self.extension(),
+            self.reserve_hash(),
+            self.buy_now(),
+            self.min_increment(),
+            self.min_increment_percent(),
+            self.format(),
+            self.reveal_period(),
+            self.start_price(),
+            self.price_decrement(),
+            self.start_height(),
+            self.quantity(),
+            self.retraction_window(),
+            self.retraction_penalty(),
+            self.currency(),
+            self.asset(),
+        )
     }
-}
 
-impl<'a> Schema<&'a mut Fork> {
-    /// Returns mutable `ProofMapIndex` with wallets.
-    pub fn wallets_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, Wallet> {
-        ProofMapIndex::new(format!("{}.wallets", SERVICE_NAME), &mut self.view)
+    /// Returns a copy of the lot with the given effective deadlines
+    ///
+    /// # Arguments
+    /// - `end_height`: new height deadline
+    /// - `closes_at`: new wall-clock deadline
+    pub fn with_deadlines(self, end_height: u64, closes_at: DateTime<Utc>) -> Self {
+        Self::new(
+            self.owner(),
+            self.name(),
+            self.min_bid(),
+            self.tx_hash(),
+            self.status(),
+            end_height,
+            closes_at,
+            self.extension_window(),
+            self.extension(),
+            self.reserve_hash(),
+            self.buy_now(),
+            self.min_increment(),
+            self.min_increment_percent(),
+            self.format(),
+            self.reveal_period(),
+            self.start_price(),
+            self.price_decrement(),
+            self.start_height(),
+            self.quantity(),
+            self.retraction_window(),
+            self.retraction_penalty(),
+            self.currency(),
+            self.asset(),
+        )
     }
 
-    /// Mutable version of the `lots` method
-    pub fn lots_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Lot> {
-        ProofMapIndex::new(format!("{}.lots", SERVICE_NAME), &mut self.view)
+    /// Checks whether the lot has the given status
+    pub fn has_status(&self, status: LotStatus) -> bool {
+        self.status() == status as u8
     }
 
-    /// Mutable version of the `bid_history` method
-    pub fn bid_history_mut(&mut self, lot: &Hash) -> ProofListIndex<&mut Fork, Bid> {
-        ProofListIndex::new_in_family(format!("{}.bid_history", SERVICE_NAME), lot, &mut self.view)
+    /// Checks whether the lot still accepts bids, i.e. has been neither closed nor cancelled
+    pub fn is_open(&self) -> bool {
+        self.has_status(LotStatus::Open)
     }
 
-    /// Creates a new wallet
-    pub fn create_wallet(&mut self, key: &PublicKey, name: &str, balance: u64) {
-        let wallet = Wallet::new(key, name, balance, 0);
-        self.wallets_mut().put(key, wallet);
+    /// Checks whether the lot has been closed
+    pub fn is_closed(&self) -> bool {
+        self.has_status(LotStatus::Closed)
+    }
+
+    /// Checks whether the lot has been cancelled by its owner
+    pub fn is_cancelled(&self) -> bool {
+        self.has_status(LotStatus::Cancelled)
+    }
+
+    /// Checks whether the payment for the lot is held in escrow
+    pub fn is_in_escrow(&self) -> bool {
+        self.has_status(LotStatus::Escrow)
+    }
+
+    /// Checks whether the buyer has disputed the delivery of the lot
+    pub fn is_disputed(&self) -> bool {
+        self.has_status(LotStatus::Disputed)
     }
 
-    /// Creates a new lot
+    /// Checks whether the lot uses the given auction format
+    pub fn has_format(&self, format: AuctionFormat) -> bool {
+        self.format() == format as u8
+    }
+
+    /// Checks whether the lot accepts sealed bids
+    pub fn is_sealed_bid(&self) -> bool {
+        AuctionFormat::from_raw(self.format()).map_or(false, AuctionFormat::is_sealed)
+    }
+
+    /// Returns the price of a Dutch auction lot at the given height, it decreases
+    /// by `price_decrement` with every block but never drops below `min_bid`
     ///
     /// # Arguments
-    /// - `owner`: lot creator's public key
-    /// - `name`: name of the lot
-    /// - `min_bid`: starting bid amount
-    pub fn create_lot(&mut self, owner: &PublicKey, name: &str, min_bid: u64, hash: &Hash) {
-        let lot = Lot::new(owner, name, min_bid, hash);
-        self.lots_mut().put(hash, lot);
+    /// `height` - height of the block the price is accepted in
+    pub fn current_price(&self, height: Height) -> u64 {
+        let blocks = height.0.saturating_sub(self.start_height());
+        let decrease = blocks.saturating_mul(self.price_decrement());
+        max(self.start_price().saturating_sub(decrease), self.min_bid())
+    }
+
+    /// Returns the amount of units sold in the lot
+    pub fn units(&self) -> u64 {
+        max(self.quantity(), 1)
+    }
+
+    /// Checks whether the lot consists of several units sold at a uniform price
+    pub fn is_multi_unit(&self) -> bool {
+        self.units() > 1
+    }
+
+    /// Returns the height at which the lot is settled automatically, which is the end of
+    /// the reveal phase for sealed-bid lots (0 if the lot has no height deadline)
+    pub fn settlement_height(&self) -> u64 {
+        if self.end_height() == 0 {
+            0
+        } else {
+            self.end_height() + self.reveal_period()
+        }
+    }
+
+    /// Checks whether the lot can be included in bundle bids, i.e. it is a single item
+    /// English auction lot settled automatically at a height deadline which can't move
+    pub fn accepts_bundle_bids(&self) -> bool {
+        self.has_format(AuctionFormat::English)
+            && !self.is_multi_unit()
+            && self.end_height() != 0
+            && !self.has_time_deadline()
+            && self.extension_window() == 0
+            && self.buy_now() == 0
+            && !self.has_reserve()
+    }
+
+    /// Returns an error if the lot has been closed or cancelled
+    pub fn check_open(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            Err(Error::LotCancelled)?
+        }
+
+        if !self.is_open() {
+            Err(Error::LotClosed)?
+        }
+
+        Ok(())
     }
 
-    /// Attempts to place a new bid on a given lot
+    /// Returns the minimum amount of a bid outbidding the given one.
+    /// The required increment is the larger of the lot's absolute and percentage increments,
+    /// but at least 1.
     ///
     /// # Arguments
-    /// - `owner`: lot creator's public key
-    /// - `name`: name of the lot
-    /// - `min_bid`: starting bid amount
-    pub fn place_bid(&mut self, owner: &PublicKey, lot: &Hash, amount: u64) -> Result<(), ExecutionError> {
-        match self.last_bid(lot) {
-            Some(bid) => {
-                match self.wallet(bid.owner()) {
-                    Some(wallet) => {
-                        if amount <= bid.amount() {
-                            Err(Error::BidTooLow)?
-                        }
-
-                        self.wallets_mut().put(bid.owner(), wallet.release(bid.amount()));
-                    },
-                    None => {},
-                };
-            },
-            None => {},
+    /// `current` - amount of the current highest bid
+    pub fn next_min_bid(&self, current: u64) -> u64 {
+        let percent_increment = match current.checked_mul(self.min_increment_percent()) {
+            Some(val) => val / 100 + if val % 100 == 0 { 0 } else { 1 },
+            None => u64::max_value(),
         };
 
-        let wallet = match self.wallet(owner) {
-            Some(val) => val.freeze(amount)?,
-            None => Err(Error::InsufficientCurrencyAmount)?,
-        };
+        let increment = max(max(self.min_increment(), percent_increment), 1);
+        current.saturating_add(increment)
+    }
 
-        let bid = Bid::new(owner, amount, lot);
-        self.bid_history_mut(lot).push(bid);
-        self.wallets_mut().put(owner, wallet);
-        Ok(())
+    /// Checks whether a bid of the given amount reaches the lot's buy-now price, if it has one
+    pub fn reaches_buy_now(&self, amount: u64) -> bool {
+        self.buy_now() != 0 && amount >= self.buy_now()
+    }
+
+    /// Checks whether an asset is sold in the lot
+    pub fn has_asset(&self) -> bool {
+        *self.asset() != Hash::zero()
+    }
+
+    /// Checks whether the lot has a hidden reserve price
+    pub fn has_reserve(&self) -> bool {
+        *self.reserve_hash() != Hash::zero()
+    }
+
+    /// Checks whether the lot has a wall-clock deadline
+    pub fn has_time_deadline(&self) -> bool {
+        self.closes_at() != no_time_deadline()
+    }
+
+    /// Checks whether the lot has a deadline of any kind
+    pub fn has_deadline(&self) -> bool {
+        self.end_height() != 0 || self.has_time_deadline()
+    }
+
+    /// Checks whether any of the lot's deadlines has passed
+    ///
+    /// # Arguments
+    /// - `height`: height of the block being checked
+    /// - `time`: current consensus time, if known
+    pub fn is_expired(&self, height: Height, time: Option<DateTime<Utc>>) -> bool {
+        let height_expired = self.end_height() != 0 && height.0 > self.end_height();
+        let time_expired = self.has_time_deadline() && time.map_or(false, |now| now >= self.closes_at());
+        height_expired || time_expired
+    }
+
+    /// Checks whether the lot can be settled, i.e. its deadlines and the reveal phase
+    /// of sealed bids (if any) have passed
+    ///
+    /// # Arguments
+    /// - `height`: height of the block being checked
+    /// - `time`: current consensus time, if known
+    pub fn is_finished(&self, height: Height, time: Option<DateTime<Utc>>) -> bool {
+        self.is_expired(height, time) && height.0 > self.settlement_height()
+    }
+}
+
+/// Wallet and treasury changes of a settlement. The changes are checked and kept aside until
+/// the whole settlement has succeeded, and are then written at once by `Schema::apply_payments`.
+/// Lots and escrows are also settled in `before_commit`, where a failed settlement isn't rolled
+/// back, so it must not leave some of the wallets changed.
+#[derive(Debug, Default)]
+struct Payments {
+    wallets: Vec<Wallet>,
+    treasury: Vec<(Hash, u64)>,
+}
+
+impl Payments {
+    /// Returns a wallet including the changes made so far
+    fn wallet<T: AsRef<dyn Snapshot>>(&self, schema: &Schema<T>, key: &PublicKey) -> Option<Wallet> {
+        match self.wallets.iter().find(|wallet| wallet.pub_key() == key) {
+            Some(wallet) => Some(wallet.clone()),
+            None => schema.wallet(key),
+        }
+    }
+
+    /// Replaces a wallet with its changed version
+    fn put(&mut self, wallet: Wallet) {
+        self.wallets.retain(|val| val.pub_key() != wallet.pub_key());
+        self.wallets.push(wallet);
+    }
+
+    /// Releases a given amount of a wallet's frozen funds, see `Schema::release_frozen`
+    fn release<T: AsRef<dyn Snapshot>>(&mut self, schema: &Schema<T>, owner: &PublicKey, currency: &Hash, amount: u64) -> Result<(), ExecutionError> {
+        let wallet = match self.wallet(schema, owner) {
+            Some(val) => val.release(currency, amount)?,
+            None => Err(Error::WalletNotFound)?,
+        };
+        self.put(wallet);
+        Ok(())
+    }
+
+    /// Releases the funds frozen for a lot's highest bid, its proxy bid has to be dropped by the caller
+    fn release_highest_bid<T: AsRef<dyn Snapshot>>(&mut self, schema: &Schema<T>, lot: &Hash) -> Result<(), ExecutionError> {
+        match schema.last_bid(lot) {
+            Some(bid) => {
+                let frozen = schema.frozen_for_bid(lot, &bid);
+                self.release(schema, bid.owner(), &schema.lot_currency(lot), frozen)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Pays a given amount from the payer's frozen funds to the payee's available funds,
+    /// see `Schema::pay_frozen`
+    fn pay<T: AsRef<dyn Snapshot>>(&mut self, schema: &Schema<T>, payer: &PublicKey, payee: &PublicKey, currency: &Hash, amount: u64) -> Result<(), ExecutionError> {
+        let payer_wallet = match self.wallet(schema, payer) {
+            Some(val) => val.withdraw_frozen(currency, amount)?,
+            None => Err(Error::WalletNotFound)?,
+        };
+        self.put(payer_wallet);
+
+        let payee_wallet = match self.wallet(schema, payee) {
+            Some(val) => val.deposit(currency, amount)?,
+            None => Err(Error::WalletNotFound)?,
+        };
+        self.put(payee_wallet);
+        Ok(())
+    }
+
+    /// Pays the price of a sale out of the buyer's frozen funds, the commission set
+    /// in the service configuration goes to the treasury and the rest to the seller
+    ///
+    /// # Arguments
+    /// - `buyer`: public key of the buyer
+    /// - `seller`: public key of the seller
+    /// - `currency`: identifier of the currency
+    /// - `amount`: the price
+    fn pay_seller<T: AsRef<dyn Snapshot>>(&mut self, schema: &Schema<T>, buyer: &PublicKey, seller: &PublicKey, currency: &Hash, amount: u64) -> Result<(), ExecutionError> {
+        let config = schema.config();
+        let treasury = match config.treasury {
+            Some(val) => val,
+            None => return self.pay(schema, buyer, seller, currency, amount),
+        };
+
+        // The configuration caps the percentage at 100, so the commission never exceeds the amount
+        let commission = (u128::from(amount) * u128::from(config.commission_percent) / 100) as u64;
+
+        self.pay(schema, buyer, seller, currency, amount - commission)?;
+        let wallet = match self.wallet(schema, buyer) {
+            Some(val) => val.withdraw_frozen(currency, commission)?,
+            None => Err(Error::WalletNotFound)?,
+        };
+        self.put(wallet);
+        self.credit_treasury(schema, &treasury, currency, commission)
+    }
+
+    /// Deposits funds to the treasury wallet, creating it if needed, and adds them
+    /// to the treasury total of the currency
+    fn credit_treasury<T: AsRef<dyn Snapshot>>(&mut self, schema: &Schema<T>, treasury: &PublicKey, currency: &Hash, amount: u64) -> Result<(), ExecutionError> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let total = match self.treasury.iter().find(|&&(ref id, _)| id == currency) {
+            Some(&(_, val)) => val,
+            None => schema.treasury().get(currency).unwrap_or(0),
+        };
+        let total = match total.checked_add(amount) {
+            Some(val) => val,
+            None => Err(Error::BalanceOverflow)?,
+        };
+
+        let wallet = self
+            .wallet(schema, treasury)
+            .unwrap_or_else(|| Wallet::new(treasury, "Treasury", Vec::new()))
+            .deposit(currency, amount)?;
+        self.put(wallet);
+        self.treasury.retain(|&(ref id, _)| id != currency);
+        self.treasury.push((*currency, total));
+        Ok(())
+    }
+}
+
+/// DB schema
+#[derive(Debug)]
+pub struct Schema<T> {
+    view: T,
+}
+
+impl<T> AsMut<T> for Schema<T> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.view
+    }
+}
+
+impl<T> Schema<T>
+    where
+        T: AsRef<dyn Snapshot>,
+{
+    /// Creates a new schema from the database view.
+    pub fn new(view: T) -> Self {
+        Schema { view }
+    }
+
+    /// Returns `ProofMapIndex` with wallets.
+    pub fn wallets(&self) -> ProofMapIndex<&T, PublicKey, Wallet> {
+        ProofMapIndex::new(format!("{}.wallets", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns a `ProofMapIndex` with lots.
+    pub fn lots(&self) -> ProofMapIndex<&T, Hash, Lot> {
+        ProofMapIndex::new(format!("{}.lots", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns bid history for a lot with the given hash.
+    pub fn bid_history(&self, hash: &Hash) -> ProofListIndex<&T, Bid> {
+        ProofListIndex::new_in_family(format!("{}.bid_history", SERVICE_NAME), hash, &self.view)
+    }
+
+    /// Returns a `ProofMapIndex` with the total supply of each currency, i.e. the amount
+    /// of funds ever issued to wallets.
+    pub fn supply(&self) -> ProofMapIndex<&T, Hash, u64> {
+        ProofMapIndex::new(format!("{}.supply", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns a `ProofMapIndex` with the total amount of each currency ever paid
+    /// to the treasury as listing fees and commissions.
+    pub fn treasury(&self) -> ProofMapIndex<&T, Hash, u64> {
+        ProofMapIndex::new(format!("{}.treasury", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns a `ProofMapIndex` with registered currencies.
+    pub fn currencies(&self) -> ProofMapIndex<&T, Hash, Currency> {
+        ProofMapIndex::new(format!("{}.currencies", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns a registered currency with the given identifier.
+    pub fn currency(&self, id: &Hash) -> Option<Currency> {
+        self.currencies().get(id)
+    }
+
+    /// Checks whether funds can be held in the currency, i.e. it is either native or registered.
+    pub fn currency_exists(&self, id: &Hash) -> bool {
+        *id == native_currency() || self.currencies().contains(id)
+    }
+
+    /// Returns the currency of a lot with the given hash, or the native currency
+    /// if there is no such lot.
+    pub fn lot_currency(&self, id: &Hash) -> Hash {
+        self.lot(id).map_or_else(native_currency, |lot| *lot.currency())
+    }
+
+    /// Returns deadline extensions history for a lot with the given hash.
+    pub fn extensions(&self, hash: &Hash) -> ProofListIndex<&T, Extension> {
+        ProofListIndex::new_in_family(format!("{}.extensions", SERVICE_NAME), hash, &self.view)
+    }
+
+    /// Returns the set of lots which are closed automatically at the given height.
+    pub fn expiring_lots(&self, height: Height) -> KeySetIndex<&T, Hash> {
+        KeySetIndex::new_in_family(format!("{}.expiring_lots", SERVICE_NAME), &height.0, &self.view)
+    }
+
+    /// Returns the set of lots with a wall-clock deadline, which are closed automatically
+    /// by the first block whose consensus time has reached it.
+    pub fn timed_lots(&self) -> KeySetIndex<&T, Hash> {
+        KeySetIndex::new(format!("{}.timed_lots", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns a `ProofMapIndex` with escrowed payments, keyed by the hash of the sold lot.
+    pub fn escrows(&self) -> ProofMapIndex<&T, Hash, Escrow> {
+        ProofMapIndex::new(format!("{}.escrows", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns the set of lots whose escrowed payments are released automatically at the given height.
+    pub fn expiring_escrows(&self, height: Height) -> KeySetIndex<&T, Hash> {
+        KeySetIndex::new_in_family(format!("{}.expiring_escrows", SERVICE_NAME), &height.0, &self.view)
+    }
+
+    /// Returns a `ProofMapIndex` with summaries of disputes, keyed by the hash of the disputed lot.
+    pub fn disputes(&self) -> ProofMapIndex<&T, Hash, Dispute> {
+        ProofMapIndex::new(format!("{}.disputes", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns steps of the dispute over a lot with the given hash.
+    pub fn dispute_history(&self, lot: &Hash) -> ProofListIndex<&T, DisputeStep> {
+        ProofListIndex::new_in_family(format!("{}.dispute_history", SERVICE_NAME), lot, &self.view)
+    }
+
+    /// Returns sealed bids committed to a lot with the given hash.
+    pub fn sealed_bids(&self, hash: &Hash) -> ProofListIndex<&T, SealedBid> {
+        ProofListIndex::new_in_family(format!("{}.sealed_bids", SERVICE_NAME), hash, &self.view)
+    }
+
+    /// Returns `ProofMapIndex` with the proxy bid backing each lot's highest bid.
+    pub fn proxy_bids(&self) -> ProofMapIndex<&T, Hash, ProxyBid> {
+        ProofMapIndex::new(format!("{}.proxy_bids", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns `ProofMapIndex` with pending bundle bids.
+    pub fn bundle_bids(&self) -> ProofMapIndex<&T, Hash, BundleBid> {
+        ProofMapIndex::new(format!("{}.bundle_bids", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns hashes of the bundle bids settled at the given height in the order of placement.
+    pub fn expiring_bundle_bids(&self, height: Height) -> ListIndex<&T, Hash> {
+        ListIndex::new_in_family(format!("{}.expiring_bundle_bids", SERVICE_NAME), &height.0, &self.view)
+    }
+
+    /// Returns a `ProofMapIndex` with unique assets.
+    pub fn assets(&self) -> ProofMapIndex<&T, Hash, Asset> {
+        ProofMapIndex::new(format!("{}.assets", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns `ProofMapIndex` with fungible assets.
+    pub fn fungible_assets(&self) -> ProofMapIndex<&T, Hash, FungibleAsset> {
+        ProofMapIndex::new(format!("{}.fungible_assets", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns units of a fungible asset held by wallets, excluding units offered by sell orders.
+    pub fn asset_holdings(&self, asset: &Hash) -> ProofMapIndex<&T, PublicKey, u64> {
+        ProofMapIndex::new_in_family(format!("{}.asset_holdings", SERVICE_NAME), asset, &self.view)
+    }
+
+    /// Returns a `ProofMapIndex` with summaries of the holdings and trades of fungible assets,
+    /// keyed by the asset identifier.
+    pub fn asset_ledgers(&self) -> ProofMapIndex<&T, Hash, AssetLedger> {
+        ProofMapIndex::new(format!("{}.asset_ledgers", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns `ProofMapIndex` with orders resting in the order books.
+    pub fn orders(&self) -> ProofMapIndex<&T, Hash, Order> {
+        ProofMapIndex::new(format!("{}.orders", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns hashes of the orders on one side of an asset's order book in the order of
+    /// their priority, see `Order::book_key`.
+    pub fn order_book(&self, asset: &Hash, side: OrderSide) -> MapIndex<&T, Vec<u8>, Hash> {
+        MapIndex::new_in_family(order_book_name(side), asset, &self.view)
+    }
+
+    /// Returns trades of a fungible asset.
+    pub fn trades(&self, asset: &Hash) -> ProofListIndex<&T, Trade> {
+        ProofListIndex::new_in_family(format!("{}.trades", SERVICE_NAME), asset, &self.view)
+    }
+
+    /// Returns an `Entry` with the amount of orders placed so far.
+    pub fn order_sequence(&self) -> Entry<&T, u64> {
+        Entry::new(format!("{}.order_sequence", SERVICE_NAME), &self.view)
+    }
+
+    /// Returns the height of the block currently being created.
+    pub fn height(&self) -> Height {
+        blockchain::Schema::new(&self.view).height().next()
+    }
+
+    /// Returns the actual service configuration. A configuration which fails to deserialize,
+    /// e.g. because of a `commission_percent` above 100, is ignored and the last valid one
+    /// applied before it stays in effect.
+    pub fn config(&self) -> AuctionConfig {
+        let height = self.height();
+        let schema = blockchain::Schema::new(&self.view);
+        let references = schema.configs_actual_from().iter().collect::<Vec<_>>();
+        references
+            .iter()
+            .rev()
+            .filter(|reference| reference.actual_from() <= height)
+            .filter_map(|reference| schema.configuration_by_hash(reference.cfg_hash()))
+            .filter_map(|config| config.services.get(SERVICE_NAME).and_then(|value| serde_json::from_value(value.clone()).ok()))
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// Returns the current consensus time provided by the time oracle, if available.
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        TimeSchema::new(&self.view).time().get()
+    }
+
+    /// Returns the wallet for the given public key.
+    pub fn wallet(&self, pub_key: &PublicKey) -> Option<Wallet> {
+        self.wallets().get(pub_key)
+    }
+
+    /// Returns a lot by its hash.
+    pub fn lot(&self, id: &Hash) -> Option<Lot> {
+        self.lots().get(id)
+    }
+
+    /// Returns the last bid in a lot's bid history which hasn't been retracted or lapsed,
+    /// i.e. the lot's highest bid
+    pub fn last_bid(&self, id: &Hash) -> Option<Bid> {
+        self.last_bid_index(id).map(|(_, bid)| bid)
+    }
+
+    /// Returns the last bid in a lot's bid history which hasn't been retracted or lapsed
+    /// along with its index in the history
+    pub fn last_bid_index(&self, id: &Hash) -> Option<(u64, Bid)> {
+        if self.lot(id).is_none() {
+            return None;
+        }
+
+        let history = self.bid_history(id);
+        (0..history.len())
+            .rev()
+            .filter_map(|i| history.get(i).map(|bid| (i, bid)))
+            .find(|&(_, ref bid)| bid.is_active())
+    }
+
+    /// Returns the amount a lot is sold for without bundle bids, which is its highest bid
+    /// or `min_bid` if there are no bids
+    pub fn standalone_price(&self, lot: &Lot) -> u64 {
+        self.last_bid(lot.tx_hash()).map_or(lot.min_bid(), |bid| bid.amount())
+    }
+
+    /// Returns the escrowed payment for a lot with the given hash.
+    pub fn escrow(&self, lot: &Hash) -> Option<Escrow> {
+        self.escrows().get(lot)
+    }
+
+    /// Returns a pending bundle bid by its hash.
+    pub fn bundle_bid(&self, id: &Hash) -> Option<BundleBid> {
+        self.bundle_bids().get(id)
+    }
+
+    /// Returns pending bundle bids including the given lot in the order of placement.
+    pub fn lot_bundle_bids(&self, lot: &Lot) -> Vec<BundleBid> {
+        self.expiring_bundle_bids(Height(lot.end_height()))
+            .iter()
+            .filter_map(|id| self.bundle_bid(&id))
+            .filter(|bundle| bundle.lots().contains(lot.tx_hash()))
+            .collect()
+    }
+
+    /// Returns a unique asset by its identifier.
+    pub fn asset(&self, id: &Hash) -> Option<Asset> {
+        self.assets().get(id)
+    }
+
+    /// Returns a fungible asset by its identifier.
+    pub fn fungible_asset(&self, id: &Hash) -> Option<FungibleAsset> {
+        self.fungible_assets().get(id)
+    }
+
+    /// Returns the amount of an asset's units held by a wallet.
+    pub fn asset_balance(&self, asset: &Hash, owner: &PublicKey) -> u64 {
+        self.asset_holdings(asset).get(owner).unwrap_or(0)
+    }
+
+    /// Returns an order resting in an order book by its hash.
+    pub fn order(&self, id: &Hash) -> Option<Order> {
+        self.orders().get(id)
+    }
+
+    /// Returns orders on one side of an asset's order book in the order of their priority.
+    pub fn book_orders(&self, asset: &Hash, side: OrderSide) -> Vec<Order> {
+        self.order_book(asset, side)
+            .values()
+            .filter_map(|id| self.order(&id))
+            .collect()
+    }
+
+    /// Returns the amount frozen for a lot's highest bid, which is the proxy maximum
+    /// if the bid has been placed automatically
+    pub fn frozen_for_bid(&self, lot: &Hash, bid: &Bid) -> u64 {
+        match self.proxy_bids().get(lot) {
+            Some(ref proxy) if proxy.owner() == bid.owner() => proxy.max_amount(),
+            _ => bid.amount(),
+        }
+    }
+
+    /// Returns the total supply of a currency, i.e. the amount of funds ever issued to wallets.
+    pub fn total_issuance(&self, currency: &Hash) -> u64 {
+        self.supply().get(currency).unwrap_or(0)
+    }
+
+    /// Returns the sum of available and frozen funds in a currency over all wallets
+    /// or `None` on overflow.
+    pub fn wallets_total(&self, currency: &Hash) -> Option<u64> {
+        self.wallets()
+            .values()
+            .fold(Some(0u64), |total, wallet| {
+                let balance = wallet.balance(currency);
+                total
+                    .and_then(|val| val.checked_add(balance.available()))
+                    .and_then(|val| val.checked_add(balance.frozen()))
+            })
+    }
+
+    /// Checks that funds are neither created nor destroyed by the service.
+    ///
+    /// Settled payments are credited to the recipients' wallets, so the funds held
+    /// by all wallets must add up exactly to the total issuance of each currency.
+    pub fn check_supply_invariant(&self) -> bool {
+        self.supply()
+            .iter()
+            .all(|(currency, total)| self.wallets_total(&currency) == Some(total))
+    }
+
+    /// Returns the service state hash
+    pub fn state_hash(&self) -> Vec<Hash> {
+        vec![
+            self.wallets().merkle_root(),
+            self.supply().merkle_root(),
+            self.currencies().merkle_root(),
+            self.assets().merkle_root(),
+            self.escrows().merkle_root(),
+            self.disputes().merkle_root(),
+            self.treasury().merkle_root(),
+            self.fungible_assets().merkle_root(),
+            self.orders().merkle_root(),
+            self.asset_ledgers().merkle_root(),
+        ]
+    }
+}
+
+/// Selects the winning bundle bids out of the given candidates, i.e. the combination
+/// of bundles without common lots bringing the largest total gain. The search is exhaustive,
+/// among combinations with equal gains the one containing earlier bundles wins.
+///
+/// # Arguments
+/// `candidates` - gain of each bundle over selling its lots separately and the bundle's lots
+fn select_bundles(candidates: &[(u64, &[Hash])]) -> Vec<usize> {
+    let mut best = (0, Vec::new());
+    search_bundles(candidates, 0, &mut Vec::new(), 0, &mut best);
+    best.1
+}
+
+/// Visits all combinations of non-overlapping bundles extending `taken` with bundles
+/// starting from `index` and records the first one with the largest gain
+fn search_bundles(
+    candidates: &[(u64, &[Hash])],
+    index: usize,
+    taken: &mut Vec<usize>,
+    gain: u64,
+    best: &mut (u64, Vec<usize>),
+) {
+    if gain > best.0 {
+        *best = (gain, taken.clone());
+    }
+
+    for i in index..candidates.len() {
+        let (bundle_gain, lots) = candidates[i];
+        let overlaps = taken
+            .iter()
+            .any(|&j| candidates[j].1.iter().any(|lot| lots.contains(lot)));
+        if !overlaps {
+            taken.push(i);
+            // Gains don't overflow, they are bounded by the funds frozen for the bundles
+            search_bundles(candidates, i + 1, taken, gain + bundle_gain, best);
+            taken.pop();
+        }
+    }
+}
+
+/// Splits the amount of a bundle bid between its lots in proportion to their standalone prices
+/// (equally if all of them are 0), the rounding remainder goes to the last lot. If the amount
+/// exceeds the sum of the prices, every lot gets at least its standalone price.
+fn split_bundle_amount(amount: u64, prices: &[u64]) -> Vec<u64> {
+    let total = prices.iter().map(|&price| u128::from(price)).sum::<u128>();
+    let mut shares = prices
+        .iter()
+        .map(|&price| {
+            if total == 0 {
+                amount / prices.len() as u64
+            } else {
+                (u128::from(amount) * u128::from(price) / total) as u64
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let split = shares.iter().sum::<u64>();
+    if let Some(last) = shares.last_mut() {
+        *last += amount - split;
+    }
+    shares
+}
+
+/// Returns the index name of one side of the order books
+fn order_book_name(side: OrderSide) -> String {
+    match side {
+        OrderSide::Buy => format!("{}.order_book.bids", SERVICE_NAME),
+        OrderSide::Sell => format!("{}.order_book.asks", SERVICE_NAME),
+    }
+}
+
+impl<'a> Schema<&'a mut Fork> {
+    /// Returns mutable `ProofMapIndex` with wallets.
+    pub fn wallets_mut(&mut self) -> ProofMapIndex<&mut Fork, PublicKey, Wallet> {
+        ProofMapIndex::new(format!("{}.wallets", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `lots` method
+    pub fn lots_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Lot> {
+        ProofMapIndex::new(format!("{}.lots", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `bid_history` method
+    pub fn bid_history_mut(&mut self, lot: &Hash) -> ProofListIndex<&mut Fork, Bid> {
+        ProofListIndex::new_in_family(format!("{}.bid_history", SERVICE_NAME), lot, &mut self.view)
+    }
+
+    /// Mutable version of the `sealed_bids` method
+    pub fn sealed_bids_mut(&mut self, lot: &Hash) -> ProofListIndex<&mut Fork, SealedBid> {
+        ProofListIndex::new_in_family(format!("{}.sealed_bids", SERVICE_NAME), lot, &mut self.view)
+    }
+
+    /// Mutable version of the `proxy_bids` method
+    pub fn proxy_bids_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, ProxyBid> {
+        ProofMapIndex::new(format!("{}.proxy_bids", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `bundle_bids` method
+    pub fn bundle_bids_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, BundleBid> {
+        ProofMapIndex::new(format!("{}.bundle_bids", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `expiring_bundle_bids` method
+    pub fn expiring_bundle_bids_mut(&mut self, height: Height) -> ListIndex<&mut Fork, Hash> {
+        ListIndex::new_in_family(format!("{}.expiring_bundle_bids", SERVICE_NAME), &height.0, &mut self.view)
+    }
+
+    /// Mutable version of the `assets` method
+    pub fn assets_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Asset> {
+        ProofMapIndex::new(format!("{}.assets", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `fungible_assets` method
+    pub fn fungible_assets_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, FungibleAsset> {
+        ProofMapIndex::new(format!("{}.fungible_assets", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `asset_holdings` method
+    pub fn asset_holdings_mut(&mut self, asset: &Hash) -> ProofMapIndex<&mut Fork, PublicKey, u64> {
+        ProofMapIndex::new_in_family(format!("{}.asset_holdings", SERVICE_NAME), asset, &mut self.view)
+    }
+
+    /// Mutable version of the `asset_ledgers` method
+    pub fn asset_ledgers_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, AssetLedger> {
+        ProofMapIndex::new(format!("{}.asset_ledgers", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `orders` method
+    pub fn orders_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Order> {
+        ProofMapIndex::new(format!("{}.orders", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `order_book` method
+    pub fn order_book_mut(&mut self, asset: &Hash, side: OrderSide) -> MapIndex<&mut Fork, Vec<u8>, Hash> {
+        MapIndex::new_in_family(order_book_name(side), asset, &mut self.view)
+    }
+
+    /// Mutable version of the `trades` method
+    pub fn trades_mut(&mut self, asset: &Hash) -> ProofListIndex<&mut Fork, Trade> {
+        ProofListIndex::new_in_family(format!("{}.trades", SERVICE_NAME), asset, &mut self.view)
+    }
+
+    /// Mutable version of the `order_sequence` method
+    pub fn order_sequence_mut(&mut self) -> Entry<&mut Fork, u64> {
+        Entry::new(format!("{}.order_sequence", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `supply` method
+    pub fn supply_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, u64> {
+        ProofMapIndex::new(format!("{}.supply", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `currencies` method
+    pub fn currencies_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Currency> {
+        ProofMapIndex::new(format!("{}.currencies", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `extensions` method
+    pub fn extensions_mut(&mut self, lot: &Hash) -> ProofListIndex<&mut Fork, Extension> {
+        ProofListIndex::new_in_family(format!("{}.extensions", SERVICE_NAME), lot, &mut self.view)
+    }
+
+    /// Mutable version of the `expiring_lots` method
+    pub fn expiring_lots_mut(&mut self, height: Height) -> KeySetIndex<&mut Fork, Hash> {
+        KeySetIndex::new_in_family(format!("{}.expiring_lots", SERVICE_NAME), &height.0, &mut self.view)
+    }
+
+    /// Mutable version of the `timed_lots` method
+    pub fn timed_lots_mut(&mut self) -> KeySetIndex<&mut Fork, Hash> {
+        KeySetIndex::new(format!("{}.timed_lots", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `escrows` method
+    pub fn escrows_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Escrow> {
+        ProofMapIndex::new(format!("{}.escrows", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `expiring_escrows` method
+    pub fn expiring_escrows_mut(&mut self, height: Height) -> KeySetIndex<&mut Fork, Hash> {
+        KeySetIndex::new_in_family(format!("{}.expiring_escrows", SERVICE_NAME), &height.0, &mut self.view)
+    }
+
+    /// Mutable version of the `treasury` method
+    pub fn treasury_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, u64> {
+        ProofMapIndex::new(format!("{}.treasury", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `disputes` method
+    pub fn disputes_mut(&mut self) -> ProofMapIndex<&mut Fork, Hash, Dispute> {
+        ProofMapIndex::new(format!("{}.disputes", SERVICE_NAME), &mut self.view)
+    }
+
+    /// Mutable version of the `dispute_history` method
+    pub fn dispute_history_mut(&mut self, lot: &Hash) -> ProofListIndex<&mut Fork, DisputeStep> {
+        ProofListIndex::new_in_family(format!("{}.dispute_history", SERVICE_NAME), lot, &mut self.view)
+    }
+
+    /// Creates a new empty wallet
+    pub fn create_wallet(&mut self, key: &PublicKey, name: &str) {
+        let wallet = Wallet::new(key, name, Vec::new());
+        self.wallets_mut().put(key, wallet);
+    }
+
+    /// Registers a new currency, it is identified by its `id`
+    pub fn register_currency(&mut self, currency: Currency) {
+        self.currencies_mut().put(currency.id(), currency.clone());
+    }
+
+    /// Issues new funds to a wallet, the amount is added to the total supply of the currency
+    ///
+    /// # Arguments
+    /// - `to`: public key of the receiving wallet
+    /// - `currency`: identifier of the issued currency
+    /// - `amount`: the amount to issue
+    pub fn issue(&mut self, to: &PublicKey, currency: &Hash, amount: u64) -> Result<(), ExecutionError> {
+        let issuance = match self.total_issuance(currency).checked_add(amount) {
+            Some(val) => val,
+            None => Err(Error::BalanceOverflow)?,
+        };
+
+        let wallet = match self.wallet(to) {
+            Some(val) => val.deposit(currency, amount)?,
+            None => Err(Error::ReceiverNotFound)?,
+        };
+
+        self.wallets_mut().put(to, wallet);
+        self.supply_mut().put(currency, issuance);
+        Ok(())
+    }
+
+    /// Transfers a given amount from the sender's available funds to the receiver's wallet,
+    /// neither wallet is modified unless both of them can be updated
+    ///
+    /// # Arguments
+    /// - `from`: public key of the sender
+    /// - `to`: public key of the receiver
+    /// - `currency`: identifier of the transferred currency
+    /// - `amount`: the amount to transfer
+    pub fn transfer(&mut self, from: &PublicKey, to: &PublicKey, currency: &Hash, amount: u64) -> Result<(), ExecutionError> {
+        if from == to {
+            Err(Error::SelfTransfer)?
+        }
+
+        let sender = match self.wallet(from) {
+            Some(val) => val.withdraw(currency, amount)?,
+            None => Err(Error::WalletNotFound)?,
+        };
+        let receiver = match self.wallet(to) {
+            Some(val) => val.deposit(currency, amount)?,
+            None => Err(Error::ReceiverNotFound)?,
+        };
+
+        self.wallets_mut().put(from, sender);
+        self.wallets_mut().put(to, receiver);
+        Ok(())
+    }
+
+    /// Issues a unique asset to its owner
+    ///
+    /// # Arguments
+    /// - `asset`: the asset to persist, it is identified by its `id`
+    pub fn issue_asset(&mut self, asset: Asset) {
+        self.assets_mut().put(asset.id(), asset.clone());
+    }
+
+    /// Creates a new lot, the budget of a reverse auction lot is frozen in the owner's wallet
+    /// and the asset sold in the lot (if any) is locked until the lot is settled
+    ///
+    /// # Arguments
+    /// - `lot`: the lot to persist, it is identified by its `tx_hash`
+    pub fn create_lot(&mut self, lot: Lot) -> Result<(), ExecutionError> {
+        if lot.has_asset() {
+            let asset = match self.asset(lot.asset()) {
+                Some(val) => val.with_lot(lot.tx_hash()),
+                None => Err(Error::AssetNotFound)?,
+            };
+            self.assets_mut().put(lot.asset(), asset);
+        }
+
+        if lot.has_format(AuctionFormat::Reverse) {
+            let wallet = match self.wallet(lot.owner()) {
+                Some(val) => val.freeze(lot.currency(), lot.min_bid())?,
+                None => Err(Error::WalletNotFound)?,
+            };
+            self.wallets_mut().put(lot.owner(), wallet);
+        }
+
+        if lot.end_height() != 0 {
+            self.expiring_lots_mut(Height(lot.settlement_height())).insert(*lot.tx_hash());
+        }
+        if lot.has_time_deadline() {
+            self.timed_lots_mut().insert(*lot.tx_hash());
+        }
+        self.lots_mut().put(lot.tx_hash(), lot.clone());
+        Ok(())
+    }
+
+    /// Attempts to place a new bid on a given lot. If the current highest bid is backed
+    /// by a proxy bid of another participant, the proxy outbids the new bid up to its maximum.
+    /// A bid at or above the lot's buy-now price buys the lot at that price, unless the earlier
+    /// proxy bid reaches the buy-now price as well, in which case the proxy bidder buys it.
+    /// Bids on reverse auction lots have to be lower than the current best one instead.
+    ///
+    /// # Arguments
+    /// - `owner`: bidder's public key
+    /// - `lot`: hash of the lot
+    /// - `amount`: bid amount
+    pub fn place_bid(&mut self, owner: &PublicKey, lot: &Hash, amount: u64) -> Result<(), ExecutionError> {
+        let proxy = self.proxy_bids().get(lot);
+        match self.lot(lot) {
+            Some(ref val) if val.has_format(AuctionFormat::Reverse) => return self.place_reverse_bid(owner, lot, amount),
+            Some(ref val) if val.reaches_buy_now(amount) => {
+                let buyer = match proxy {
+                    Some(ref proxy) if proxy.owner() != owner && val.reaches_buy_now(proxy.max_amount()) => *proxy.owner(),
+                    _ => *owner,
+                };
+                return self.sell(&buyer, val, val.buy_now());
+            }
+            Some(_) => {}
+            None => Err(Error::LotNotFound)?,
+        }
+
+        if let (Some(lot), Some(last)) = (self.lot(lot), self.last_bid(lot)) {
+            let next_min_bid = lot.next_min_bid(last.amount());
+            if amount > last.amount() && amount < next_min_bid {
+                let description = format!("{}, next acceptable amount is {}", Error::BidIncrementTooSmall, next_min_bid);
+                Err(ExecutionError::with_description(Error::BidIncrementTooSmall as u8, description))?
+            }
+        }
+
+        let bid = self.record_bid(owner, lot, amount)?;
+
+        match proxy {
+            Some(ref proxy) if proxy.owner() != owner && proxy.max_amount() >= amount => self.outbid_with_proxy(lot, proxy, amount),
+            _ => self.extend_deadlines(lot, &bid),
+        }
+    }
+
+    /// Places a bid on a reverse auction lot, bidders are paid at close so no funds are frozen,
+    /// but they need a wallet to be paid to
+    fn place_reverse_bid(&mut self, owner: &PublicKey, lot: &Hash, amount: u64) -> Result<(), ExecutionError> {
+        if self.wallet(owner).is_none() {
+            Err(Error::WalletNotFound)?
+        }
+
+        if let Some(last) = self.last_bid(lot) {
+            if amount >= last.amount() {
+                Err(Error::BidTooHigh)?
+            }
+        }
+
+        let bid = Bid::new(owner, amount, lot, 1, self.height().0, false, false);
+        self.bid_history_mut(lot).push(bid.clone());
+        self.extend_deadlines(lot, &bid)
+    }
+
+    /// Places a bid for several units of a multi-unit lot. Bids don't outbid each other,
+    /// the unit price multiplied by the quantity stays frozen until the lot is settled.
+    ///
+    /// # Arguments
+    /// - `owner`: bidder's public key
+    /// - `lot`: hash of the lot
+    /// - `amount`: unit price
+    /// - `quantity`: amount of units
+    pub fn place_multi_unit_bid(&mut self, owner: &PublicKey, lot: &Hash, amount: u64, quantity: u64) -> Result<(), ExecutionError> {
+        let frozen = match amount.checked_mul(quantity) {
+            Some(val) => val,
+            None => Err(Error::BalanceOverflow)?,
+        };
+
+        let currency = self.lot_currency(lot);
+        let wallet = match self.wallet(owner) {
+            Some(val) => val.freeze(&currency, frozen)?,
+            None => Err(Error::InsufficientCurrencyAmount)?,
+        };
+
+        let bid = Bid::new(owner, amount, lot, quantity, self.height().0, false, false);
+        self.bid_history_mut(lot).push(bid.clone());
+        self.wallets_mut().put(owner, wallet);
+        self.extend_deadlines(lot, &bid)
+    }
+
+    /// Places a proxy bid on a given lot. The maximum amount is frozen and the lowest
+    /// winning bid is placed on the bidder's behalf. A competing proxy bid with a higher
+    /// or equal maximum (ties are won by the earlier proxy) outbids the new one instead.
+    /// If the winning proxy has to bid the lot's buy-now price or more, its owner buys the lot
+    /// at the buy-now price.
+    ///
+    /// # Arguments
+    /// - `owner`: bidder's public key
+    /// - `lot`: hash of the lot
+    /// - `max_amount`: maximum amount to bid
+    pub fn place_proxy_bid(&mut self, owner: &PublicKey, lot: &Hash, max_amount: u64) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        let min_amount = match self.last_bid(lot.tx_hash()) {
+            Some(bid) => lot.next_min_bid(bid.amount()),
+            None => lot.min_bid(),
+        };
+
+        let bid = match self.proxy_bids().get(lot.tx_hash()) {
+            Some(ref proxy) if proxy.owner() == owner => {
+                return self.raise_proxy_bid(lot.tx_hash(), proxy, max_amount);
+            }
+            Some(ref proxy) if proxy.max_amount() >= max_amount => {
+                if max_amount < min_amount {
+                    Err(Error::BidTooLow)?
+                }
+                if lot.reaches_buy_now(max_amount) {
+                    return self.sell(proxy.owner(), &lot, lot.buy_now());
+                }
+                self.record_bid(owner, lot.tx_hash(), max_amount)?;
+                return self.outbid_with_proxy(lot.tx_hash(), proxy, max_amount);
+            }
+            Some(ref proxy) => {
+                let amount = lot.next_min_bid(proxy.max_amount());
+                if max_amount < amount {
+                    Err(Error::BidTooLow)?
+                }
+                if lot.reaches_buy_now(amount) {
+                    return self.sell(owner, &lot, lot.buy_now());
+                }
+                if self.last_bid(lot.tx_hash()).map_or(true, |bid| bid.amount() < proxy.max_amount()) {
+                    self.push_bid(proxy.owner(), lot.tx_hash(), proxy.max_amount(), proxy.max_amount())?;
+                }
+                self.push_proxy_bid(owner, lot.tx_hash(), amount, max_amount)?
+            }
+            None => {
+                if max_amount < min_amount {
+                    Err(Error::BidTooLow)?
+                }
+                if lot.reaches_buy_now(min_amount) {
+                    return self.sell(owner, &lot, lot.buy_now());
+                }
+                self.push_proxy_bid(owner, lot.tx_hash(), min_amount, max_amount)?
+            }
+        };
+
+        self.extend_deadlines(lot.tx_hash(), &bid)
+    }
+
+    /// Raises the maximum of the bidder's own leading proxy bid, freezing the difference
+    fn raise_proxy_bid(&mut self, lot: &Hash, proxy: &ProxyBid, max_amount: u64) -> Result<(), ExecutionError> {
+        if max_amount <= proxy.max_amount() {
+            Err(Error::BidTooLow)?
+        }
+
+        let currency = self.lot_currency(lot);
+        let wallet = match self.wallet(proxy.owner()) {
+            Some(val) => val.freeze(&currency, max_amount - proxy.max_amount())?,
+            None => Err(Error::WalletNotFound)?,
+        };
+        self.wallets_mut().put(proxy.owner(), wallet);
+        self.proxy_bids_mut().put(lot, ProxyBid::new(proxy.owner(), max_amount));
+        Ok(())
+    }
+
+    /// Places the lowest bid of a proxy bidder beating the competing amount,
+    /// a competing amount equal to the proxy maximum is beaten by the proxy placed earlier.
+    /// If the bid reaches the lot's buy-now price, the proxy bidder buys the lot at that price instead.
+    fn outbid_with_proxy(&mut self, lot: &Hash, proxy: &ProxyBid, competing: u64) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        let amount = lot.next_min_bid(competing).min(proxy.max_amount());
+        if lot.reaches_buy_now(amount) {
+            return self.sell(proxy.owner(), &lot, lot.buy_now());
+        }
+
+        let bid = self.push_proxy_bid(proxy.owner(), lot.tx_hash(), amount, proxy.max_amount())?;
+        self.extend_deadlines(lot.tx_hash(), &bid)
+    }
+
+    /// Retracts the highest bid on a lot, releasing its frozen funds and charging the lot's
+    /// retraction penalty to the bidder. Earlier bids of the same bidder are retracted as well.
+    /// The previous bid of another participant who can still fund it becomes the highest one
+    /// again and its amount is frozen, bids which can't be funded anymore lapse.
+    ///
+    /// # Arguments
+    /// `lot` - hash of the lot
+    pub fn retract_bid(&mut self, lot: &Hash) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        let (index, bid) = match self.last_bid_index(lot.tx_hash()) {
+            Some(val) => val,
+            None => Err(Error::NotHighestBidder)?,
+        };
+
+        self.release_highest_bid(lot.tx_hash())?;
+        self.bid_history_mut(lot.tx_hash()).set(index, bid.clone().mark_retracted());
+
+        let wallet = match self.wallet(bid.owner()) {
+            Some(val) => val.freeze(lot.currency(), lot.retraction_penalty())?,
+            None => Err(Error::WalletNotFound)?,
+        };
+        self.wallets_mut().put(bid.owner(), wallet);
+        self.pay_frozen(bid.owner(), lot.owner(), lot.currency(), lot.retraction_penalty())?;
+
+        for i in (0..index).rev() {
+            let previous = match self.bid_history(lot.tx_hash()).get(i) {
+                Some(ref val) if val.is_active() => val.clone(),
+                _ => continue,
+            };
+
+            if previous.owner() == bid.owner() {
+                self.bid_history_mut(lot.tx_hash()).set(i, previous.mark_retracted());
+                continue;
+            }
+
+            match self.wallet(previous.owner()).map(|wallet| wallet.freeze(lot.currency(), previous.amount())) {
+                Some(Ok(wallet)) => {
+                    self.wallets_mut().put(previous.owner(), wallet);
+                    break;
+                }
+                _ => self.bid_history_mut(lot.tx_hash()).set(i, previous.mark_lapsed()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Places an all-or-nothing bid for several lots sharing the same height deadline,
+    /// the amount stays frozen until the lots are settled
+    ///
+    /// # Arguments
+    /// - `owner`: bidder's public key
+    /// - `lots`: hashes of the lots
+    /// - `amount`: amount offered for all lots together
+    /// - `tx_hash`: hash of the transaction placing the bid, used as its identifier
+    pub fn place_bundle_bid(&mut self, owner: &PublicKey, lots: &[Hash], amount: u64, tx_hash: &Hash) -> Result<(), ExecutionError> {
+        let first = match lots.first().and_then(|lot| self.lot(lot)) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        let wallet = match self.wallet(owner) {
+            Some(val) => val.freeze(first.currency(), amount)?,
+            None => Err(Error::InsufficientCurrencyAmount)?,
+        };
+
+        self.wallets_mut().put(owner, wallet);
+        self.bundle_bids_mut().put(tx_hash, BundleBid::new(owner, lots, amount, tx_hash));
+        self.expiring_bundle_bids_mut(Height(first.end_height())).push(*tx_hash);
+        Ok(())
+    }
+
+    /// Commits to a sealed bid and freezes the deposit backing it
+    ///
+    /// # Arguments
+    /// - `owner`: bidder's public key
+    /// - `lot`: hash of the lot
+    /// - `commitment`: commitment to the bid amount, see `amount_commitment`
+    /// - `deposit`: amount to freeze, the revealed bid can't exceed it
+    pub fn commit_bid(&mut self, owner: &PublicKey, lot: &Hash, commitment: &Hash, deposit: u64) -> Result<(), ExecutionError> {
+        if self.sealed_bids(lot).iter().any(|bid| bid.owner() == owner) {
+            Err(Error::BidAlreadyCommitted)?
+        }
+
+        let currency = self.lot_currency(lot);
+        let wallet = match self.wallet(owner) {
+            Some(val) => val.freeze(&currency, deposit)?,
+            None => Err(Error::InsufficientCurrencyAmount)?,
+        };
+
+        self.sealed_bids_mut(lot).push(SealedBid::new(owner, commitment, deposit, 0, false));
+        self.wallets_mut().put(owner, wallet);
+        Ok(())
+    }
+
+    /// Reveals a previously committed sealed bid
+    ///
+    /// # Arguments
+    /// - `owner`: bidder's public key
+    /// - `lot`: hash of the lot
+    /// - `amount`: bid amount
+    /// - `salt`: salt used for the commitment
+    pub fn reveal_bid(&mut self, owner: &PublicKey, lot: &Hash, amount: u64, salt: &Hash) -> Result<(), ExecutionError> {
+        let (index, bid) = match self.sealed_bids(lot).iter().enumerate().find(|&(_, ref bid)| bid.owner() == owner) {
+            Some(val) => val,
+            None => Err(Error::CommitmentNotFound)?,
+        };
+
+        if bid.revealed() {
+            Err(Error::BidAlreadyRevealed)?
+        }
+
+        if amount_commitment(amount, salt) != *bid.commitment() || amount > bid.deposit() {
+            Err(Error::InvalidBidReveal)?
+        }
+
+        let revealed = SealedBid::new(owner, bid.commitment(), bid.deposit(), amount, true);
+        self.sealed_bids_mut(lot).set(index as u64, revealed);
+        Ok(())
+    }
+
+    /// Buys a lot at its buy-now price, releasing the current highest bid
+    /// and paying the seller immediately
+    ///
+    /// # Arguments
+    /// - `buyer`: buyer's public key
+    /// - `lot`: hash of the lot
+    pub fn buy_now(&mut self, buyer: &PublicKey, lot: &Hash) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        let price = lot.buy_now();
+        self.sell(buyer, &lot, price)
+    }
+
+    /// Sells a Dutch auction lot at its current price
+    ///
+    /// # Arguments
+    /// - `buyer`: buyer's public key
+    /// - `lot`: hash of the lot
+    pub fn accept_price(&mut self, buyer: &PublicKey, lot: &Hash) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        let price = lot.current_price(self.height());
+        self.sell(buyer, &lot, price)
+    }
+
+    /// Records the buyer's bid at the given price and completes the sale, see `complete_sale`
+    fn sell(&mut self, buyer: &PublicKey, lot: &Lot, price: u64) -> Result<(), ExecutionError> {
+        let bid = self.record_bid(buyer, lot.tx_hash(), price)?;
+        self.complete_sale(Payments::default(), lot, buyer, bid.amount())
+    }
+
+    /// Checks that the new bid exceeds the current highest bid and records it
+    fn record_bid(&mut self, owner: &PublicKey, lot: &Hash, amount: u64) -> Result<Bid, ExecutionError> {
+        if let Some(bid) = self.last_bid(lot) {
+            if amount <= bid.amount() {
+                Err(Error::BidTooLow)?
+            }
+        }
+
+        self.push_bid(owner, lot, amount, amount)
+    }
+
+    /// Records a bid backed by a proxy bid with the given maximum
+    fn push_proxy_bid(&mut self, owner: &PublicKey, lot: &Hash, amount: u64, max_amount: u64) -> Result<Bid, ExecutionError> {
+        let bid = self.push_bid(owner, lot, amount, max_amount)?;
+        self.proxy_bids_mut().put(lot, ProxyBid::new(owner, max_amount));
+        Ok(bid)
+    }
+
+    /// Releases the frozen amount of the current highest bid, freezes the given amount
+    /// for the new bid and appends the new bid to the lot's history
+    fn push_bid(&mut self, owner: &PublicKey, lot: &Hash, amount: u64, frozen: u64) -> Result<Bid, ExecutionError> {
+        self.release_highest_bid(lot)?;
+
+        let currency = self.lot_currency(lot);
+        let wallet = match self.wallet(owner) {
+            Some(val) => val.freeze(&currency, frozen)?,
+            None => Err(Error::InsufficientCurrencyAmount)?,
+        };
+
+        let bid = Bid::new(owner, amount, lot, 1, self.height().0, false, false);
+        self.bid_history_mut(lot).push(bid.clone());
+        self.wallets_mut().put(owner, wallet);
+        Ok(bid)
+    }
+
+    /// Releases the funds frozen for a lot's highest bid and drops its proxy bid
+    fn release_highest_bid(&mut self, lot: &Hash) -> Result<(), ExecutionError> {
+        let mut payments = Payments::default();
+        payments.release_highest_bid(self, lot)?;
+        self.apply_payments(payments);
+        self.proxy_bids_mut().remove(lot);
+        Ok(())
+    }
+
+    /// Moves a lot's deadlines forward if the given bid has been placed
+    /// within the lot's extension window, and records the extension
+    ///
+    /// # Arguments
+    /// - `lot`: hash of the lot
+    /// - `bid`: the bid that has just been placed
+    fn extend_deadlines(&mut self, lot: &Hash, bid: &Bid) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => return Ok(()),
+        };
+
+        if lot.extension_window() == 0 {
+            return Ok(());
+        }
+
+        let height = self.height().0;
+        let mut end_height = lot.end_height();
+        if end_height != 0 && end_height - height < lot.extension_window() {
+            end_height = match end_height.checked_add(lot.extension()) {
+                Some(val) => val,
+                None => Err(Error::DeadlineOverflow)?,
+            };
+        }
+
+        let mut closes_at = lot.closes_at();
+        if lot.has_time_deadline() {
+            if let Some(now) = self.time() {
+                if closes_at - now < Duration::seconds(lot.extension_window() as i64) {
+                    closes_at = match closes_at.checked_add_signed(Duration::seconds(lot.extension() as i64)) {
+                        Some(val) => val,
+                        None => Err(Error::DeadlineOverflow)?,
+                    };
+                }
+            }
+        }
+
+        if end_height != lot.end_height() {
+            self.expiring_lots_mut(Height(lot.end_height())).remove(lot.tx_hash());
+            self.expiring_lots_mut(Height(end_height)).insert(*lot.tx_hash());
+        }
+
+        if end_height != lot.end_height() || closes_at != lot.closes_at() {
+            let extension = Extension::new(bid.owner(), bid.amount(), end_height, closes_at);
+            self.extensions_mut(lot.tx_hash()).push(extension);
+            self.lots_mut().put(lot.tx_hash(), lot.clone().with_deadlines(end_height, closes_at));
+        }
+        Ok(())
+    }
+
+    /// Closes a lot and completes its sale to the winner (if any), see `complete_sale`,
+    /// the rest of a winning proxy bid is released. If the winning bid is below the reserve
+    /// price, its frozen amount is released back to the bidder and the asset is unlocked instead.
+    /// Every payment is checked before anything is written, so a lot failing to close is left
+    /// as it was.
+    ///
+    /// # Arguments
+    /// - `lot`: hash of the lot to close
+    /// - `reserve`: revealed reserve price (0 if the lot has no reserve)
+    pub fn close_lot(&mut self, lot: &Hash, reserve: u64) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        lot.check_open()?;
+
+        let mut payments = Payments::default();
+        let sale = if lot.is_sealed_bid() {
+            self.settle_sealed_bids(&mut payments, &lot, reserve)?
+        } else if lot.is_multi_unit() {
+            self.settle_multi_unit_bids(&mut payments, &lot, reserve)?;
+            None
+        } else if lot.has_format(AuctionFormat::Reverse) {
+            self.settle_reverse_bids(&mut payments, &lot)?;
+            None
+        } else {
+            self.settle_highest_bid(&mut payments, &lot, reserve)?
+        };
+
+        match sale {
+            Some((ref buyer, price)) => self.complete_sale(payments, &lot, buyer, price)?,
+            None => {
+                self.apply_payments(payments);
+                self.settle_asset(&lot, None);
+                self.lots_mut().put(lot.tx_hash(), lot.clone().with_status(LotStatus::Closed));
+            }
+        }
+
+        self.proxy_bids_mut().remove(lot.tx_hash());
+        if let (true, Some((ref winner, price))) = (lot.is_sealed_bid(), sale) {
+            let bid = Bid::new(winner, price, lot.tx_hash(), 1, self.height().0, false, false);
+            self.bid_history_mut(lot.tx_hash()).push(bid);
+        }
+        Ok(())
+    }
+
+    /// Determines the winner of an open auction and releases the rest of a winning proxy bid,
+    /// returns the winner and the price, which stays frozen in the winner's wallet
+    fn settle_highest_bid(&self, payments: &mut Payments, lot: &Lot, reserve: u64) -> Result<Option<(PublicKey, u64)>, ExecutionError> {
+        match self.last_bid(lot.tx_hash()) {
+            Some(ref bid) if bid.amount() < reserve => {
+                payments.release_highest_bid(self, lot.tx_hash())?;
+                Ok(None)
+            }
+            Some(bid) => {
+                let unused = self.frozen_for_bid(lot.tx_hash(), &bid) - bid.amount();
+                payments.release(self, bid.owner(), lot.currency(), unused)?;
+                Ok(Some((*bid.owner(), bid.amount())))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Completes the sale of a lot whose price is frozen in the buyer's wallet, after applying
+    /// the rest of the settlement's payments. If escrow is enabled by the service configuration,
+    /// the payment stays frozen until the buyer confirms delivery or the escrow period passes,
+    /// otherwise the lot is paid out right away.
+    fn complete_sale(&mut self, payments: Payments, lot: &Lot, buyer: &PublicKey, price: u64) -> Result<(), ExecutionError> {
+        let period = self.config().escrow_period;
+        if period == 0 {
+            return self.pay_out(payments, lot, buyer, price);
+        }
+
+        let release_height = match self.height().0.checked_add(period) {
+            Some(val) => val,
+            None => Err(Error::EscrowOverflow)?,
+        };
+        self.apply_payments(payments);
+        self.escrows_mut().put(lot.tx_hash(), Escrow::new(buyer, price, release_height));
+        self.expiring_escrows_mut(Height(release_height)).insert(*lot.tx_hash());
+        self.lots_mut().put(lot.tx_hash(), lot.clone().with_status(LotStatus::Escrow));
+        Ok(())
+    }
+
+    /// Pays the price frozen in the buyer's wallet to the lot owner together with the rest
+    /// of the settlement's payments, transfers the lot's asset to the buyer and closes the lot
+    fn pay_out(&mut self, mut payments: Payments, lot: &Lot, buyer: &PublicKey, price: u64) -> Result<(), ExecutionError> {
+        payments.pay_seller(self, buyer, lot.owner(), lot.currency(), price)?;
+        self.apply_payments(payments);
+        self.settle_asset(lot, Some(buyer));
+        self.lots_mut().put(lot.tx_hash(), lot.clone().with_status(LotStatus::Closed));
+        Ok(())
+    }
+
+    /// Releases the escrowed payment for a lot to its owner and closes the lot, see `pay_out`
+    ///
+    /// # Arguments
+    /// `lot` - hash of the lot
+    pub fn release_escrow(&mut self, lot: &Hash) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        let escrow = match self.escrow(lot.tx_hash()) {
+            Some(ref val) if lot.is_in_escrow() => val.clone(),
+            _ => Err(Error::NotInEscrow)?,
+        };
+
+        self.pay_out(Payments::default(), &lot, escrow.buyer(), escrow.amount())?;
+        self.expiring_escrows_mut(Height(escrow.release_height())).remove(lot.tx_hash());
+        self.escrows_mut().remove(lot.tx_hash());
+        Ok(())
+    }
+
+    /// Opens a dispute over a lot in escrow, the payment stays frozen until the dispute is resolved
+    ///
+    /// # Arguments
+    /// - `lot`: hash of the lot
+    /// - `step`: the step opening the dispute
+    pub fn open_dispute(&mut self, lot: &Hash, step: DisputeStep) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        let escrow = match self.escrow(lot.tx_hash()) {
+            Some(ref val) if lot.is_in_escrow() => val.clone(),
+            _ => Err(Error::NotInEscrow)?,
+        };
+
+        self.expiring_escrows_mut(Height(escrow.release_height())).remove(lot.tx_hash());
+        self.push_dispute_step(lot.tx_hash(), step);
+        self.lots_mut().put(lot.tx_hash(), lot.clone().with_status(LotStatus::Disputed));
+        Ok(())
+    }
+
+    /// Resolves a dispute by refunding the buyer's share of the escrowed payment and paying
+    /// the rest to the lot owner, and closes the lot. The lot's asset is transferred to the buyer
+    /// unless the whole payment is refunded.
+    ///
+    /// # Arguments
+    /// - `lot`: hash of the lot
+    /// - `step`: the step resolving the dispute, its amounts add up to the escrowed payment
+    pub fn resolve_dispute(&mut self, lot: &Hash, step: DisputeStep) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        let escrow = match self.escrow(lot.tx_hash()) {
+            Some(ref val) if lot.is_disputed() => val.clone(),
+            _ => Err(Error::DisputeNotFound)?,
+        };
+
+        if step.buyer_amount().checked_add(step.seller_amount()) != Some(escrow.amount()) {
+            Err(Error::InvalidSplit)?
+        }
+
+        let mut payments = Payments::default();
+        payments.release(self, escrow.buyer(), lot.currency(), step.buyer_amount())?;
+        payments.pay_seller(self, escrow.buyer(), lot.owner(), lot.currency(), step.seller_amount())?;
+        self.apply_payments(payments);
+        let winner = if step.seller_amount() > 0 { Some(escrow.buyer()) } else { None };
+        self.settle_asset(&lot, winner);
+
+        self.escrows_mut().remove(lot.tx_hash());
+        self.push_dispute_step(lot.tx_hash(), step);
+        self.lots_mut().put(lot.tx_hash(), lot.clone().with_status(LotStatus::Closed));
+        Ok(())
+    }
+
+    /// Appends a step to a lot's dispute history and updates the dispute summary
+    fn push_dispute_step(&mut self, lot: &Hash, step: DisputeStep) {
+        let dispute = {
+            let mut history = self.dispute_history_mut(lot);
+            history.push(step);
+            Dispute::new(history.len(), &history.merkle_root())
+        };
+        self.disputes_mut().put(lot, dispute);
+    }
+
+    /// Releases the escrowed payments whose escrow period ends at the current height
+    pub fn release_expired_escrows(&mut self) {
+        let height = self.height();
+        let expired = self.expiring_escrows(height).iter().collect::<Vec<_>>();
+
+        for lot in &expired {
+            // A payment failing to be released changes nothing, the lot stays in escrow
+            // and can still be confirmed or disputed by the buyer
+            let _ = self.release_escrow(lot);
+        }
+    }
+
+    /// Unlocks the asset sold in a lot (if any) and transfers it to the winner, if there is one
+    fn settle_asset(&mut self, lot: &Lot, winner: Option<&PublicKey>) {
+        if let Some(asset) = self.asset(lot.asset()) {
+            let owner = *winner.unwrap_or_else(|| asset.owner());
+            self.assets_mut().put(lot.asset(), asset.with_owner(&owner));
+        }
+    }
+
+    /// Determines the winning bundle bids among the bundles settled at the given height.
+    /// A bundle wins if it brings more than selling its lots separately, see `select_bundles`.
+    /// The highest bids on the lots of a winning bundle are released and replaced with
+    /// the bundle owner's bids splitting the bundle amount, see `split_bundle_amount`,
+    /// which are then paid when the lots are closed. Losing bundles are released.
+    fn settle_bundle_bids(&mut self, height: Height) -> Result<(), ExecutionError> {
+        let bundles = self
+            .expiring_bundle_bids(height)
+            .iter()
+            .filter_map(|id| self.bundle_bid(&id))
+            .collect::<Vec<_>>();
+
+        let mut prices = Vec::with_capacity(bundles.len());
+        let mut candidates = Vec::with_capacity(bundles.len());
+        for (i, bundle) in bundles.iter().enumerate() {
+            let lots = bundle.lots().iter().filter_map(|id| self.lot(id)).collect::<Vec<_>>();
+            let lot_prices = lots.iter().map(|lot| self.standalone_price(lot)).collect::<Vec<_>>();
+            let standalone = lot_prices.iter().fold(0u64, |total, &price| total.saturating_add(price));
+
+            if lots.len() == bundle.lots().len() && lots.iter().all(Lot::is_open) && bundle.amount() > standalone {
+                candidates.push((i, bundle.amount() - standalone));
+            }
+            prices.push(lot_prices);
+        }
+
+        let winners = {
+            let gains = candidates
+                .iter()
+                .map(|&(i, gain)| (gain, bundles[i].lots()))
+                .collect::<Vec<_>>();
+            select_bundles(&gains).into_iter().map(|j| candidates[j].0).collect::<Vec<_>>()
+        };
+
+        // Every release is checked before anything is written
+        let mut payments = Payments::default();
+        for (i, bundle) in bundles.iter().enumerate() {
+            if winners.contains(&i) {
+                for lot in bundle.lots() {
+                    payments.release_highest_bid(self, lot)?;
+                }
+            } else {
+                let currency = self.lot_currency(&bundle.lots()[0]);
+                payments.release(self, bundle.owner(), &currency, bundle.amount())?;
+            }
+        }
+        self.apply_payments(payments);
+
+        for (i, bundle) in bundles.iter().enumerate() {
+            if winners.contains(&i) {
+                let shares = split_bundle_amount(bundle.amount(), &prices[i]);
+                for (lot, share) in bundle.lots().iter().zip(shares) {
+                    self.proxy_bids_mut().remove(lot);
+                    let bid = Bid::new(bundle.owner(), share, bundle.tx_hash(), 1, height.0, false, false);
+                    self.bid_history_mut(lot).push(bid);
+                }
+            }
+            self.bundle_bids_mut().remove(bundle.tx_hash());
+        }
+
+        // See `close_expired_lots` for why the index is not cleared
+        self.expiring_bundle_bids_mut(height).truncate(0);
+        Ok(())
+    }
+
+    /// Releases all bundle bids settled at the given height without awarding any of them,
+    /// a deposit failing to be released is left as it is
+    fn release_expired_bundle_bids(&mut self, height: Height) {
+        let bundles = self
+            .expiring_bundle_bids(height)
+            .iter()
+            .filter_map(|id| self.bundle_bid(&id))
+            .collect::<Vec<_>>();
+
+        for bundle in &bundles {
+            let currency = self.lot_currency(&bundle.lots()[0]);
+            let _ = self.release_frozen(bundle.owner(), &currency, bundle.amount());
+            self.bundle_bids_mut().remove(bundle.tx_hash());
+        }
+
+        // See `close_expired_lots` for why the index is not cleared
+        self.expiring_bundle_bids_mut(height).truncate(0);
+    }
+
+    /// Releases pending bundle bids including the given lot
+    fn release_bundle_bids(&mut self, lot: &Lot) -> Result<(), ExecutionError> {
+        for bundle in self.lot_bundle_bids(lot) {
+            self.release_frozen(bundle.owner(), lot.currency(), bundle.amount())?;
+            self.bundle_bids_mut().remove(bundle.tx_hash());
+        }
+        Ok(())
+    }
+
+    /// Pays the lowest bid of a reverse auction out of the owner's budget and releases the rest
+    fn settle_reverse_bids(&self, payments: &mut Payments, lot: &Lot) -> Result<(), ExecutionError> {
+        let paid = match self.last_bid(lot.tx_hash()) {
+            Some(bid) => {
+                payments.pay_seller(self, lot.owner(), bid.owner(), lot.currency(), bid.amount())?;
+                bid.amount()
+            }
+            None => 0,
+        };
+        payments.release(self, lot.owner(), lot.currency(), lot.min_bid() - paid)
+    }
+
+    /// Allocates the units of a multi-unit lot to the highest bids (earlier bids first among
+    /// equal unit prices) and charges every winner the clearing price, which is the lowest
+    /// winning unit price. Frozen funds not spent on allocated units are released.
+    fn settle_multi_unit_bids(&self, payments: &mut Payments, lot: &Lot, reserve: u64) -> Result<(), ExecutionError> {
+        let bids = self.bid_history(lot.tx_hash()).iter().collect::<Vec<_>>();
+        let mut ranked = (0..bids.len()).filter(|&i| bids[i].amount() >= reserve).collect::<Vec<_>>();
+        ranked.sort_by(|&a, &b| bids[b].amount().cmp(&bids[a].amount()));
+
+        let mut allocations = vec![0u64; bids.len()];
+        let mut remaining = lot.units();
+        let mut price = 0;
+        for i in ranked {
+            if remaining == 0 {
+                break;
+            }
+            allocations[i] = min(remaining, bids[i].quantity());
+            remaining -= allocations[i];
+            price = bids[i].amount();
+        }
+
+        for (bid, units) in bids.iter().zip(allocations) {
+            // Neither product overflows, it has been checked when the bid was placed
+            let frozen = bid.amount() * bid.quantity();
+            let paid = price * units;
+            payments.pay_seller(self, bid.owner(), lot.owner(), lot.currency(), paid)?;
+            payments.release(self, bid.owner(), lot.currency(), frozen - paid)?;
+        }
+        Ok(())
+    }
+
+    /// Determines the highest revealed sealed bid, releasing the rest of its deposit and the deposits
+    /// of other revealed bids. Deposits of unrevealed bids are forfeited to the lot owner.
+    /// Returns the winner and the price, which is the second-highest revealed amount (but at least
+    /// `min_bid` and the reserve) for `AuctionFormat::Vickrey` lots. The price stays frozen
+    /// in the winner's wallet and is appended to the lot's bid history by `close_lot`.
+    fn settle_sealed_bids(&self, payments: &mut Payments, lot: &Lot, reserve: u64) -> Result<Option<(PublicKey, u64)>, ExecutionError> {
+        let bids = self.sealed_bids(lot.tx_hash()).iter().collect::<Vec<_>>();
+        // Ties are won by the bid committed first
+        let winner = bids
+            .iter()
+            .filter(|bid| bid.revealed() && bid.amount() >= reserve)
+            .fold(None, |best: Option<&SealedBid>, bid| match best {
+                Some(val) if val.amount() >= bid.amount() => Some(val),
+                _ => Some(bid),
+            })
+            .cloned();
+
+        let price = match winner {
+            Some(ref val) if lot.has_format(AuctionFormat::Vickrey) => {
+                let second = bids
+                    .iter()
+                    .filter(|bid| bid.revealed() && bid.owner() != val.owner())
+                    .map(|bid| bid.amount())
+                    .max()
+                    .unwrap_or_else(|| lot.min_bid());
+                max(second, reserve)
+            }
+            Some(ref val) => val.amount(),
+            None => 0,
+        };
+
+        for bid in &bids {
+            if !bid.revealed() {
+                payments.pay(self, bid.owner(), lot.owner(), lot.currency(), bid.deposit())?;
+            } else if winner.as_ref().map_or(false, |val| val.owner() == bid.owner()) {
+                payments.release(self, bid.owner(), lot.currency(), bid.deposit() - price)?;
+            } else {
+                payments.release(self, bid.owner(), lot.currency(), bid.deposit())?;
+            }
+        }
+        Ok(winner.map(|val| (*val.owner(), price)))
+    }
+
+    /// Pays a given amount from the payer's frozen funds to the payee's available funds
+    ///
+    /// # Arguments
+    /// - `payer`: public key of the paying wallet
+    /// - `payee`: public key of the receiving wallet
+    /// - `currency`: identifier of the currency
+    /// - `amount`: the amount to pay
+    pub fn pay_frozen(&mut self, payer: &PublicKey, payee: &PublicKey, currency: &Hash, amount: u64) -> Result<(), ExecutionError> {
+        let mut payments = Payments::default();
+        payments.pay(self, payer, payee, currency, amount)?;
+        self.apply_payments(payments);
+        Ok(())
+    }
+
+    /// Charges the listing fee set in the service configuration to the owner of a new lot
+    ///
+    /// # Arguments
+    /// - `owner`: public key of the lot owner
+    pub fn pay_listing_fee(&mut self, owner: &PublicKey) -> Result<(), ExecutionError> {
+        let config = self.config();
+        let treasury = match config.treasury {
+            Some(val) => val,
+            None => return Ok(()),
+        };
+
+        let mut payments = Payments::default();
+        let wallet = match self.wallet(owner) {
+            Some(val) => val.withdraw(&native_currency(), config.listing_fee)?,
+            None => Err(Error::WalletNotFound)?,
+        };
+        payments.put(wallet);
+        payments.credit_treasury(self, &treasury, &native_currency(), config.listing_fee)?;
+        self.apply_payments(payments);
+        Ok(())
+    }
+
+    /// Writes the wallet and treasury changes of a settlement, see `Payments`
+    fn apply_payments(&mut self, payments: Payments) {
+        for wallet in payments.wallets {
+            let key = *wallet.pub_key();
+            self.wallets_mut().put(&key, wallet);
+        }
+        for (currency, total) in payments.treasury {
+            self.treasury_mut().put(&currency, total);
+        }
+    }
+
+    /// Releases a given amount of a wallet's frozen funds
+    ///
+    /// # Arguments
+    /// - `owner`: public key of the wallet
+    /// - `currency`: identifier of the currency
+    /// - `amount`: the amount to release
+    pub fn release_frozen(&mut self, owner: &PublicKey, currency: &Hash, amount: u64) -> Result<(), ExecutionError> {
+        let mut payments = Payments::default();
+        payments.release(self, owner, currency, amount)?;
+        self.apply_payments(payments);
+        Ok(())
+    }
+
+    /// Cancels a lot, releasing the frozen amounts of its bids, sealed bid deposits,
+    /// bundle bids including the lot and the budget of a reverse auction, and unlocking its asset
+    ///
+    /// # Arguments
+    /// - `lot`: hash of the lot to cancel
+    pub fn cancel_lot(&mut self, lot: &Hash) -> Result<(), ExecutionError> {
+        let lot = match self.lot(lot) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        lot.check_open()?;
+
+        if lot.has_format(AuctionFormat::Reverse) {
+            self.release_frozen(lot.owner(), lot.currency(), lot.min_bid())?;
+        } else if lot.is_multi_unit() {
+            for bid in self.bid_history(lot.tx_hash()).iter().collect::<Vec<_>>() {
+                self.release_frozen(bid.owner(), lot.currency(), bid.amount() * bid.quantity())?;
+            }
+        } else {
+            self.release_highest_bid(lot.tx_hash())?;
+        }
+
+        for bid in self.sealed_bids(lot.tx_hash()).iter().collect::<Vec<_>>() {
+            self.release_frozen(bid.owner(), lot.currency(), bid.deposit())?;
+        }
+
+        self.release_bundle_bids(&lot)?;
+        self.settle_asset(&lot, None);
+        self.lots_mut().put(lot.tx_hash(), lot.clone().with_status(LotStatus::Cancelled));
+        Ok(())
+    }
+
+    /// Closes all lots whose deadline (or the end of the reveal phase for sealed-bid lots)
+    /// is the current height, after bundle bids settled at this height have been awarded,
+    /// and all lots whose wall-clock deadline has been reached by the consensus time.
+    /// Lots with a reserve price stay open for `RESERVE_REVEAL_PERIOD` more blocks so that
+    /// the owner can reveal it with `CloseLot`, and are then closed without a sale.
+    pub fn close_expired_lots(&mut self) {
+        let height = self.height();
+        // Bundle bids failing to be settled change nothing, their deposits are released instead
+        // so that they don't stay frozen
+        if self.settle_bundle_bids(height).is_err() {
+            self.release_expired_bundle_bids(height);
+        }
+        let expired = self.expiring_lots(height).iter().collect::<Vec<_>>();
+
+        for lot in &expired {
+            let reserve = match self.lot(lot) {
+                Some(ref val) if val.has_reserve() && val.settlement_height() == height.0 => {
+                    let timeout = height.0.saturating_add(RESERVE_REVEAL_PERIOD);
+                    self.expiring_lots_mut(Height(timeout)).insert(*lot);
+                    continue;
+                }
+                // No bid meets a reserve price which hasn't been revealed in time
+                Some(ref val) if val.has_reserve() => u64::max_value(),
+                _ => 0,
+            };
+            // Lots closed or cancelled before their deadline are simply skipped, a lot failing
+            // to close changes nothing and can still be closed or cancelled by its owner
+            let _ = self.close_lot(lot, reserve);
+        }
+
+        // `clear` would also remove lots expiring at later heights, since it removes
+        // every key from the start of this height's family onwards
+        for lot in &expired {
+            self.expiring_lots_mut(height).remove(lot);
+        }
+
+        if let Some(now) = self.time() {
+            self.close_timed_lots(height, now);
+        }
+    }
+
+    /// Closes the lots whose wall-clock deadline is at or before `now`, lots with a reserve
+    /// price are scheduled to be closed at `height + RESERVE_REVEAL_PERIOD` instead. Lots which
+    /// are no longer open are dropped from the `timed_lots` set.
+    fn close_timed_lots(&mut self, height: Height, now: DateTime<Utc>) {
+        let timed = self.timed_lots().iter().collect::<Vec<_>>();
+
+        for hash in &timed {
+            let lot = match self.lot(hash) {
+                Some(val) => val,
+                None => continue,
+            };
+            // Deadlines extended by late bids are taken into account, since the lot is checked
+            // against its current `closes_at`
+            if lot.is_open() && now < lot.closes_at() {
+                continue;
+            }
+
+            self.timed_lots_mut().remove(hash);
+            if !lot.is_open() {
+                continue;
+            }
+
+            if lot.has_reserve() {
+                let timeout = height.0.saturating_add(RESERVE_REVEAL_PERIOD);
+                self.expiring_lots_mut(Height(timeout)).insert(*hash);
+            } else {
+                // Same as above, a lot failing to close can still be closed by its owner
+                let _ = self.close_lot(hash, 0);
+            }
+        }
+    }
+
+    /// Creates a fungible asset, its whole supply is credited to the issuer
+    ///
+    /// # Arguments
+    /// - `asset`: the asset to persist, it is identified by its `id`
+    pub fn create_fungible_asset(&mut self, asset: FungibleAsset) {
+        self.asset_holdings_mut(asset.id()).put(asset.issuer(), asset.supply());
+        self.fungible_assets_mut().put(asset.id(), asset.clone());
+        self.update_asset_ledger(asset.id());
+    }
+
+    /// Places an order and matches it against the opposite side of the asset's order book.
+    /// The unfilled part of the order rests in the book.
+    ///
+    /// # Arguments
+    /// - `owner`: public key of the order owner
+    /// - `asset`: identifier of the traded asset
+    /// - `side`: side of the order
+    /// - `price`: limit price per unit
+    /// - `quantity`: amount of units
+    /// - `tx_hash`: hash of the transaction placing the order, used as its identifier
+    pub fn place_order(
+        &mut self,
+        owner: &PublicKey,
+        asset: &Hash,
+        side: OrderSide,
+        price: u64,
+        quantity: u64,
+        tx_hash: &Hash,
+    ) -> Result<(), ExecutionError> {
+        match side {
+            OrderSide::Buy => {
+                let amount = match price.checked_mul(quantity) {
+                    Some(val) => val,
+                    None => Err(Error::BalanceOverflow)?,
+                };
+                let wallet = match self.wallet(owner) {
+                    Some(val) => val.freeze(&native_currency(), amount)?,
+                    None => Err(Error::WalletNotFound)?,
+                };
+                self.wallets_mut().put(owner, wallet);
+            }
+            OrderSide::Sell => {
+                let units = match self.asset_balance(asset, owner).checked_sub(quantity) {
+                    Some(val) => val,
+                    None => Err(Error::InsufficientAssetAmount)?,
+                };
+                self.asset_holdings_mut(asset).put(owner, units);
+            }
+        }
+
+        let sequence = self.order_sequence().get().unwrap_or(0);
+        self.order_sequence_mut().set(sequence + 1);
+
+        let order = Order::new(owner, asset, side as u8, price, quantity, sequence, tx_hash);
+        let order = self.match_order(order, side)?;
+
+        if order.quantity() > 0 {
+            self.order_book_mut(asset, side).put(&order.book_key(), *order.tx_hash());
+            self.orders_mut().put(order.tx_hash(), order.clone());
+        }
+        self.update_asset_ledger(asset);
+        Ok(())
+    }
+
+    /// Matches an incoming order against the best orders of the opposite side
+    /// as long as their prices cross, returns the unfilled part of the order
+    fn match_order(&mut self, mut order: Order, side: OrderSide) -> Result<Order, ExecutionError> {
+        let asset = *order.asset();
+
+        while order.quantity() > 0 {
+            let (key, id) = match self.order_book(&asset, side.opposite()).iter().next() {
+                Some(val) => val,
+                None => break,
+            };
+            let resting = match self.order(&id) {
+                Some(val) => val,
+                None => break,
+            };
+
+            if !order.crosses(&resting) {
+                break;
+            }
+
+            let quantity = min(order.quantity(), resting.quantity());
+            if order.is_buy() {
+                self.settle_trade(&order, &resting, resting.price(), quantity)?;
+            } else {
+                self.settle_trade(&resting, &order, resting.price(), quantity)?;
+            }
+
+            let remaining = order.quantity() - quantity;
+            order = order.with_quantity(remaining);
+            let remaining = resting.quantity() - quantity;
+            let resting = resting.with_quantity(remaining);
+            if resting.quantity() == 0 {
+                self.order_book_mut(&asset, side.opposite()).remove(&key);
+                self.orders_mut().remove(resting.tx_hash());
+            } else {
+                self.orders_mut().put(resting.tx_hash(), resting.clone());
+            }
+        }
+        Ok(order)
+    }
+
+    /// Pays for traded units from the funds frozen by the buy order and credits the units
+    /// taken out of the seller's holdings to the buyer. Funds frozen by the buyer above
+    /// the trade price are released.
+    fn settle_trade(&mut self, buy: &Order, sell: &Order, price: u64, quantity: u64) -> Result<(), ExecutionError> {
+        // Neither product overflows, the buy order's limit price times its quantity has been frozen
+        self.pay_frozen(buy.owner(), sell.owner(), &native_currency(), price * quantity)?;
+        self.release_frozen(buy.owner(), &native_currency(), (buy.price() - price) * quantity)?;
+        self.deposit_asset_units(buy.asset(), buy.owner(), quantity)?;
+
+        let trade = Trade::new(buy.owner(), sell.owner(), price, quantity);
+        self.trades_mut(buy.asset()).push(trade);
+        Ok(())
+    }
+
+    /// Removes an order from its order book, releasing the frozen funds of a buy order
+    /// or returning the units of a sell order to the owner's holdings
+    ///
+    /// # Arguments
+    /// - `order`: the order to cancel
+    pub fn cancel_order(&mut self, order: &Order) -> Result<(), ExecutionError> {
+        let side = if order.is_buy() { OrderSide::Buy } else { OrderSide::Sell };
+        self.order_book_mut(order.asset(), side).remove(&order.book_key());
+        self.orders_mut().remove(order.tx_hash());
+
+        match side {
+            OrderSide::Buy => self.release_frozen(order.owner(), &native_currency(), order.price() * order.quantity())?,
+            OrderSide::Sell => self.deposit_asset_units(order.asset(), order.owner(), order.quantity())?,
+        }
+        self.update_asset_ledger(order.asset());
+        Ok(())
+    }
+
+    /// Adds units of an asset to a wallet's holdings
+    fn deposit_asset_units(&mut self, asset: &Hash, owner: &PublicKey, amount: u64) -> Result<(), ExecutionError> {
+        let units = match self.asset_balance(asset, owner).checked_add(amount) {
+            Some(val) => val,
+            None => Err(Error::BalanceOverflow)?,
+        };
+        self.asset_holdings_mut(asset).put(owner, units);
+        Ok(())
+    }
+
+    /// Updates the summary of an asset's holdings and trades after either has changed
+    fn update_asset_ledger(&mut self, asset: &Hash) {
+        let holdings_hash = self.asset_holdings(asset).merkle_root();
+        let ledger = {
+            let trades = self.trades(asset);
+            AssetLedger::new(&holdings_hash, trades.len(), &trades.merkle_root())
+        };
+        self.asset_ledgers_mut().put(asset, ledger);
     }
 }
@@ -2,12 +2,17 @@
 
 #![allow(bare_trait_objects)]
 
+use chrono::{DateTime, Utc};
 use exonum::{
     blockchain::{ExecutionError, ExecutionResult, Transaction}, crypto::{CryptoHash, Hash, PublicKey},
-    messages::Message, storage::Fork,
+    helpers::Height, messages::Message, storage::{Fork, Snapshot},
 };
 
-use schema::Schema;
+use schema::{
+    amount_commitment, native_currency, no_time_deadline, Asset, AuctionFormat, Currency, DisputeAction, DisputeStep,
+    FungibleAsset, Lot, LotStatus, OrderSide, Schema, MAX_BUNDLE_BIDS, MAX_BUNDLE_LOTS, MAX_EXTENSION,
+    MAX_REVEAL_PERIOD,
+};
 use SERVICE_ID;
 
 /// Error codes returned by the service transactions
@@ -22,33 +27,395 @@ pub enum Error {
 
     /// Lot doesn't exist.
     ///
-    /// Can be emitted by `PlaceBid`.
+    /// Can be emitted by `PlaceBid`, `PlaceProxyBid`, `PlaceBundleBid`, `RetractBid`, `CommitBid`, `RevealBid`,
+    /// `BuyNow`, `AcceptPrice`, `CloseLot`, `CancelLot`, `ConfirmDelivery`, `OpenDispute` and `ResolveDispute`.
     #[fail(display = "Lot does not exist")]
     LotNotFound = 1,
 
     /// Bid too low.
     ///
-    /// Can be emitted by `PlaceBid`.
+    /// Can be emitted by `PlaceBid`, `PlaceProxyBid`, `PlaceBundleBid` and `CommitBid`.
     #[fail(display = "Bid below current minimum")]
     BidTooLow = 2,
 
     /// Insufficient currency amount.
     ///
-    /// Can be emitted by `PlaceBid`.
-    #[fail(display = "Currency amount insufficient for bid placement")]
+    /// Can be emitted by `Transfer`, `CreateLot`, `PlaceBid`, `PlaceProxyBid`, `PlaceBundleBid`, `RetractBid`,
+    /// `CommitBid`, `BuyNow`, `AcceptPrice` and `PlaceOrder`.
+    #[fail(display = "Available currency amount insufficient for the operation")]
     InsufficientCurrencyAmount = 3,
 
     /// Wallet does not exist.
     ///
-    /// Can be emitted by `PlaceBid` and `CreateLot`.
+    /// Can be emitted by `Transfer`, `IssueAsset`, `PlaceBid`, `RetractBid`, `CreateLot`, `CloseLot`,
+    /// `CreateFungibleAsset` and `PlaceOrder`.
     #[fail(display = "Wallet does not exist")]
     WalletNotFound = 4,
 
     /// Participants can't bid for their own lots.
     ///
-    /// Can be emitted by `PlaceBid`.
+    /// Can be emitted by `PlaceBid`, `PlaceProxyBid`, `PlaceBundleBid`, `CommitBid`, `BuyNow` and `AcceptPrice`.
     #[fail(display = "Bidding not allowed on one's own lot")]
     BiddingNotAllowedOnOwnLot = 5,
+
+    /// Lot is already closed.
+    ///
+    /// Can be emitted by `PlaceBid`, `PlaceProxyBid`, `PlaceBundleBid`, `RetractBid`, `CommitBid`, `RevealBid`,
+    /// `BuyNow`, `AcceptPrice`, `CloseLot` and `CancelLot`.
+    #[fail(display = "Lot is closed")]
+    LotClosed = 6,
+
+    /// Only the lot owner can close or cancel it.
+    ///
+    /// Can be emitted by `CloseLot` and `CancelLot`.
+    #[fail(display = "Operation allowed only for the lot owner")]
+    NotLotOwner = 7,
+
+    /// Lot deadline (either block height or consensus time) has passed.
+    ///
+    /// Can be emitted by `PlaceBid`, `PlaceProxyBid`, `PlaceBundleBid`, `RetractBid`, `BuyNow`, `AcceptPrice`
+    /// and `CancelLot`.
+    #[fail(display = "Lot deadline has passed")]
+    LotExpired = 8,
+
+    /// Lot deadline is not in the future, can't be parsed or doesn't suit the auction format.
+    ///
+    /// Can be emitted by `CreateLot`.
+    #[fail(display = "Lot deadline is in the past, malformed or not supported by the auction format")]
+    InvalidDeadline = 9,
+
+    /// Lots with a deadline can't be closed before it passes.
+    ///
+    /// Can be emitted by `CloseLot`.
+    #[fail(display = "Lot deadline has not been reached yet")]
+    DeadlineNotReached = 10,
+
+    /// Frozen funds are insufficient for the operation.
+    ///
+    /// Can be emitted by `PlaceBid` and `CloseLot`.
+    #[fail(display = "Frozen amount insufficient for the operation")]
+    InsufficientFrozenAmount = 11,
+
+    /// Operation would overflow a wallet balance or the total supply.
+    ///
+    /// Can be emitted by `Issue`, `Transfer`, `PlaceBid`, `RetractBid`, `CloseLot` and `PlaceOrder`.
+    #[fail(display = "Balance overflow")]
+    BalanceOverflow = 12,
+
+    /// Revealed reserve price doesn't match the lot's commitment.
+    ///
+    /// Can be emitted by `CloseLot`.
+    #[fail(display = "Revealed reserve does not match the commitment")]
+    InvalidReserve = 13,
+
+    /// Lot can't be bought instantly.
+    ///
+    /// Can be emitted by `BuyNow`.
+    #[fail(display = "Lot has no buy-now price")]
+    BuyNowNotAvailable = 14,
+
+    /// Buy-now price is below the minimum bid.
+    ///
+    /// Can be emitted by `CreateLot`.
+    #[fail(display = "Buy-now price below minimum bid")]
+    InvalidBuyNowPrice = 15,
+
+    /// Lot has been cancelled by its owner.
+    ///
+    /// Can be emitted by `PlaceBid`, `PlaceProxyBid`, `PlaceBundleBid`, `RetractBid`, `CommitBid`, `RevealBid`,
+    /// `BuyNow`, `AcceptPrice`, `CloseLot` and `CancelLot`.
+    #[fail(display = "Lot has been cancelled")]
+    LotCancelled = 16,
+
+    /// Bid doesn't exceed the current highest bid by the lot's minimum increment.
+    /// The description of the execution error contains the next acceptable amount.
+    ///
+    /// Can be emitted by `PlaceBid`.
+    #[fail(display = "Bid increment too small")]
+    BidIncrementTooSmall = 17,
+
+    /// Operation is not supported by the lot's auction format.
+    ///
+    /// Can be emitted by `CreateLot`, `PlaceBid`, `PlaceProxyBid`, `PlaceBundleBid`, `RetractBid`, `CommitBid`,
+    /// `RevealBid`, `BuyNow` and `AcceptPrice`.
+    #[fail(display = "Operation not supported by the auction format")]
+    UnsupportedAuctionFormat = 18,
+
+    /// Sealed bids can't be committed after the lot's deadline.
+    ///
+    /// Can be emitted by `CommitBid`.
+    #[fail(display = "Commit phase is over")]
+    CommitPhaseOver = 19,
+
+    /// Sealed bids can't be revealed before the lot's deadline.
+    ///
+    /// Can be emitted by `RevealBid`.
+    #[fail(display = "Reveal phase has not started yet")]
+    RevealPhaseNotStarted = 20,
+
+    /// Sealed bids can't be revealed after the lot's reveal period.
+    ///
+    /// Can be emitted by `RevealBid`.
+    #[fail(display = "Reveal phase is over")]
+    RevealPhaseOver = 21,
+
+    /// Participant has already committed to a sealed bid on the lot.
+    ///
+    /// Can be emitted by `CommitBid`.
+    #[fail(display = "Bid already committed")]
+    BidAlreadyCommitted = 22,
+
+    /// Participant has no sealed bid on the lot.
+    ///
+    /// Can be emitted by `RevealBid`.
+    #[fail(display = "Bid commitment not found")]
+    CommitmentNotFound = 23,
+
+    /// Revealed bid doesn't match the commitment, exceeds the deposit or is below the minimum bid.
+    ///
+    /// Can be emitted by `RevealBid`.
+    #[fail(display = "Revealed bid is invalid")]
+    InvalidBidReveal = 24,
+
+    /// Sealed bid has already been revealed.
+    ///
+    /// Can be emitted by `RevealBid`.
+    #[fail(display = "Bid already revealed")]
+    BidAlreadyRevealed = 25,
+
+    /// Start price of a Dutch auction lot is below its floor price.
+    ///
+    /// Can be emitted by `CreateLot`.
+    #[fail(display = "Start price below the floor price")]
+    InvalidStartPrice = 26,
+
+    /// Bid quantity is zero or exceeds the amount of units in the lot.
+    ///
+    /// Can be emitted by `PlaceBid`.
+    #[fail(display = "Invalid bid quantity")]
+    InvalidQuantity = 27,
+
+    /// Bid on a reverse auction lot exceeds the current lowest bid or the owner's budget.
+    ///
+    /// Can be emitted by `PlaceBid`.
+    #[fail(display = "Bid above current maximum")]
+    BidTooHigh = 28,
+
+    /// Fungible or unique asset doesn't exist.
+    ///
+    /// Can be emitted by `CreateLot` and `PlaceOrder`.
+    #[fail(display = "Asset does not exist")]
+    AssetNotFound = 29,
+
+    /// Asset holdings are insufficient for a sell order.
+    ///
+    /// Can be emitted by `PlaceOrder`.
+    #[fail(display = "Asset amount insufficient for the order")]
+    InsufficientAssetAmount = 30,
+
+    /// Order side is unknown or its price or quantity is zero.
+    ///
+    /// Can be emitted by `PlaceOrder`.
+    #[fail(display = "Invalid order")]
+    InvalidOrder = 31,
+
+    /// Order doesn't exist or has been filled.
+    ///
+    /// Can be emitted by `CancelOrder`.
+    #[fail(display = "Order does not exist")]
+    OrderNotFound = 32,
+
+    /// Only the order owner can cancel it.
+    ///
+    /// Can be emitted by `CancelOrder`.
+    #[fail(display = "Operation allowed only for the order owner")]
+    NotOrderOwner = 33,
+
+    /// Bundle has fewer than 2 or more than `schema::MAX_BUNDLE_LOTS` lots, repeats a lot
+    /// or contains lots which can't be bundled, see `schema::Lot::accepts_bundle_bids`,
+    /// or lots with different deadlines or currencies.
+    ///
+    /// Can be emitted by `PlaceBundleBid`.
+    #[fail(display = "Invalid bundle")]
+    InvalidBundle = 34,
+
+    /// `schema::MAX_BUNDLE_BIDS` bundle bids are already settled at the lots' deadline.
+    ///
+    /// Can be emitted by `PlaceBundleBid`.
+    #[fail(display = "Too many bundle bids for the deadline")]
+    TooManyBundleBids = 35,
+
+    /// Only the participant who placed the lot's highest bid can retract it.
+    ///
+    /// Can be emitted by `RetractBid`.
+    #[fail(display = "Operation allowed only for the highest bidder")]
+    NotHighestBidder = 36,
+
+    /// Lot doesn't allow retractions or the bid has been placed too long ago.
+    ///
+    /// Can be emitted by `RetractBid`.
+    #[fail(display = "Retraction window is over")]
+    RetractionWindowOver = 37,
+
+    /// Receiver of a transfer or issued funds doesn't exist.
+    ///
+    /// Can be emitted by `Issue` and `Transfer`.
+    #[fail(display = "Receiver does not exist")]
+    ReceiverNotFound = 38,
+
+    /// Sender and receiver of a transfer are the same wallet.
+    ///
+    /// Can be emitted by `Transfer`.
+    #[fail(display = "Transfer to oneself is not allowed")]
+    SelfTransfer = 39,
+
+    /// Only keys listed in the service configuration can register currencies and issue
+    /// the native currency, a registered currency is issued only by its issuer.
+    ///
+    /// Can be emitted by `RegisterCurrency` and `Issue`.
+    #[fail(display = "Operation allowed only for the configured issuers")]
+    NotIssuer = 40,
+
+    /// Currency is neither native nor registered.
+    ///
+    /// Can be emitted by `Issue`, `Transfer` and `CreateLot`.
+    #[fail(display = "Currency does not exist")]
+    CurrencyNotFound = 41,
+
+    /// Only the asset owner can sell it.
+    ///
+    /// Can be emitted by `CreateLot`.
+    #[fail(display = "Operation allowed only for the asset owner")]
+    NotAssetOwner = 42,
+
+    /// Asset is already sold in another open lot.
+    ///
+    /// Can be emitted by `CreateLot`.
+    #[fail(display = "Asset is locked by another lot")]
+    AssetLocked = 43,
+
+    /// Payment for the lot isn't held in escrow or is already disputed.
+    ///
+    /// Can be emitted by `ConfirmDelivery` and `OpenDispute`.
+    #[fail(display = "Lot is not in escrow")]
+    NotInEscrow = 44,
+
+    /// Only the buyer of the lot can confirm its delivery or dispute it.
+    ///
+    /// Can be emitted by `ConfirmDelivery` and `OpenDispute`.
+    #[fail(display = "Operation allowed only for the buyer")]
+    NotBuyer = 45,
+
+    /// Only keys listed in the service configuration can resolve disputes.
+    ///
+    /// Can be emitted by `ResolveDispute`.
+    #[fail(display = "Operation allowed only for the configured arbiters")]
+    NotArbiter = 46,
+
+    /// Lot isn't disputed.
+    ///
+    /// Can be emitted by `ResolveDispute`.
+    #[fail(display = "Dispute does not exist")]
+    DisputeNotFound = 47,
+
+    /// Amounts of a dispute resolution don't add up to the escrowed payment.
+    ///
+    /// Can be emitted by `ResolveDispute`.
+    #[fail(display = "Split does not match the escrowed payment")]
+    InvalidSplit = 48,
+
+    /// Lot owner can't pay the listing fee.
+    ///
+    /// Can be emitted by `CreateLot`.
+    #[fail(display = "Insufficient funds to pay the listing fee")]
+    ListingFeeUnpaid = 49,
+
+    /// Lot deadline can't be extended any further.
+    ///
+    /// Can be emitted by `PlaceBid` and `PlaceProxyBid`.
+    #[fail(display = "Lot deadline can't be extended any further")]
+    DeadlineOverflow = 50,
+
+    /// Escrowed payment can't be released at a height past the maximum.
+    ///
+    /// Can be emitted by `PlaceBid`, `PlaceProxyBid`, `BuyNow`, `AcceptPrice` and `CloseLot`.
+    #[fail(display = "Escrow release height overflow")]
+    EscrowOverflow = 51,
+
+    /// Deadline extension window or extension exceeds `schema::MAX_EXTENSION`.
+    ///
+    /// Can be emitted by `CreateLot`.
+    #[fail(display = "Lot deadline extension is too long")]
+    ExtensionTooLong = 52,
+
+    /// Reveal period is missing for a sealed-bid lot, set for another format, exceeds
+    /// `schema::MAX_REVEAL_PERIOD` or ends past the maximum height.
+    ///
+    /// Can be emitted by `CreateLot`.
+    #[fail(display = "Invalid reveal period")]
+    InvalidRevealPeriod = 53,
+
+    /// Transferred amount is zero.
+    ///
+    /// Can be emitted by `Transfer`.
+    #[fail(display = "Transfer amount must be positive")]
+    ZeroTransferAmount = 54,
+}
+
+/// Returns an open sealed-bid lot
+///
+/// # Arguments
+/// - `schema`: database schema
+/// - `lot`: hash of the lot
+fn sealed_bid_lot<T: AsRef<dyn Snapshot>>(schema: &Schema<T>, lot: &Hash) -> Result<Lot, ExecutionError> {
+    let lot = match schema.lot(lot) {
+        Some(val) => val,
+        None => Err(Error::LotNotFound)?,
+    };
+
+    lot.check_open()?;
+
+    if !lot.is_sealed_bid() {
+        Err(Error::UnsupportedAuctionFormat)?
+    }
+
+    Ok(lot)
+}
+
+/// Returns a lot of one of the given auction formats which is still open for bids
+/// from the given participant
+///
+/// # Arguments
+/// - `schema`: database schema
+/// - `lot`: hash of the lot
+/// - `bidder`: public key of the participant
+/// - `formats`: accepted auction formats of the lot
+fn open_lot<T: AsRef<dyn Snapshot>>(
+    schema: &Schema<T>,
+    lot: &Hash,
+    bidder: &PublicKey,
+    formats: &[AuctionFormat],
+) -> Result<Lot, ExecutionError> {
+    let lot = match schema.lot(lot) {
+        Some(val) => val,
+        None => Err(Error::LotNotFound)?,
+    };
+
+    if lot.is_expired(schema.height(), schema.time()) {
+        Err(Error::LotExpired)?
+    }
+
+    lot.check_open()?;
+
+    if !formats.iter().any(|&format| lot.has_format(format)) {
+        Err(Error::UnsupportedAuctionFormat)?
+    }
+
+    if lot.owner() == bidder {
+        Err(Error::BiddingNotAllowedOnOwnLot)?
+    }
+
+    Ok(lot)
 }
 
 impl From<Error> for ExecutionError {
@@ -58,19 +425,121 @@ impl From<Error> for ExecutionError {
     }
 }
 
+encoding_struct! {
+    /// Deadlines of a new lot
+    struct DeadlineOptions {
+        /// Height of the last block accepting bids, 0 for lots without a deadline
+        end_height: u64,
+        /// RFC 3339 timestamp after which no bids are accepted,
+        /// empty for lots without a wall-clock deadline
+        closes_at: &str,
+        /// Bids placed this close to a deadline extend it (in blocks for `end_height`,
+        /// in seconds for `closes_at`, 0 to disable extensions)
+        extension_window: u64,
+        /// Amount of blocks or seconds a deadline is moved forward by
+        extension: u64,
+    }
+}
+
+encoding_struct! {
+    /// Rules for bids on a new lot
+    struct BiddingOptions {
+        /// Commitment to a hidden reserve price computed with `schema::amount_commitment`,
+        /// `Hash::zero()` for lots without a reserve
+        reserve_hash: &Hash,
+        /// Price at which the lot is sold immediately, 0 if not available
+        buy_now: u64,
+        /// Minimum amount by which a bid must exceed the current highest one
+        min_increment: u64,
+        /// Minimum increment as a percentage of the current highest bid
+        min_increment_percent: u64,
+        /// Amount of blocks after placing a bid during which the leading bidder can retract it,
+        /// 0 if bids can't be retracted
+        retraction_window: u64,
+        /// Amount the bidder retracting a bid pays to the lot owner
+        retraction_penalty: u64,
+    }
+}
+
+encoding_struct! {
+    /// Auction format of a new lot with its format-specific parameters
+    struct FormatOptions {
+        /// Auction format, see `schema::AuctionFormat`
+        format: u8,
+        /// Amount of blocks after `end_height` during which sealed bids are revealed,
+        /// 0 for open auctions
+        reveal_period: u64,
+        /// Start price of a Dutch auction, `min_bid` is used as the floor price
+        start_price: u64,
+        /// Amount the price of a Dutch auction decreases by with every block
+        price_decrement: u64,
+        /// Amount of identical units sold in the lot, 0 or 1 for a single item
+        quantity: u64,
+    }
+}
+
 transactions! {
     /// Transaction group.
     pub AuctionTransactions {
         const SERVICE_ID = SERVICE_ID;
 
-        /// Create a wallet with the given `name`.
+        /// Create an empty wallet with the given `name`.
         struct CreateWallet {
             /// `PublicKey` of the new wallet.
             pub_key: &PublicKey,
             /// Name of the new wallet.
             name:    &str,
-            /// Initial balance
-            balance: u64,
+        }
+
+        /// Registration of a new currency, only allowed for the configured issuers
+        struct RegisterCurrency {
+            /// Issuer, the only participant allowed to issue the currency
+            issuer: &PublicKey,
+            /// Currency name
+            name: &str,
+            /// Amount of decimal places used to display amounts
+            decimals: u8,
+            /// Auxiliary number to guarantee non-idempotence of transactions
+            seed: u64,
+        }
+
+        /// Issuance of new funds to a wallet, the native currency is issued by the configured
+        /// issuers and a registered currency by its issuer
+        struct Issue {
+            /// Issuer
+            issuer: &PublicKey,
+            /// Receiver
+            to: &PublicKey,
+            /// ID of the currency, `schema::native_currency()` for the native one
+            currency: &Hash,
+            /// Amount to issue
+            amount: u64,
+            /// Auxiliary number to guarantee non-idempotence of transactions
+            seed: u64,
+        }
+
+        /// Transfer of funds between wallets, only available funds can be transferred
+        struct Transfer {
+            /// Sender
+            from: &PublicKey,
+            /// Receiver
+            to: &PublicKey,
+            /// ID of the currency
+            currency: &Hash,
+            /// Amount to transfer
+            amount: u64,
+            /// Auxiliary number to guarantee non-idempotence of transactions
+            seed: u64,
+        }
+
+        /// Issuance of a unique asset which can be sold in a lot
+        struct IssueAsset {
+            /// Asset owner
+            owner: &PublicKey,
+            /// Asset name
+            name: &str,
+            /// Auxiliary number to guarantee non-idempotence of transactions
+            seed: u64,
         }
 
         /// Create a lot with the given name and starting bid amount
@@ -79,8 +548,18 @@ transactions! {
             owner: &PublicKey,
             /// Lot name
             name:  &str,
-            /// Minimum bid
+            /// Minimum bid, the budget frozen in the owner's wallet for reverse auctions
             min_bid: u64,
+            /// Deadlines of the lot
+            deadlines: DeadlineOptions,
+            /// Rules for bids on the lot
+            bidding: BiddingOptions,
+            /// Auction format and its parameters
+            format: FormatOptions,
+            /// ID of the currency all amounts are in, `schema::native_currency()` for the native one
+            currency: &Hash,
+            /// ID (hash) of the asset sold in the lot, `Hash::zero()` for lots without an asset
+            asset: &Hash,
         }
 
         /// Bid placement
@@ -89,8 +568,171 @@ transactions! {
             owner: &PublicKey,
             /// ID (hash) of the lot to bid on
             lot: &Hash,
+            /// Bid amount, per unit for multi-unit lots
+            amount: u64,
+            /// Amount of units to bid for, 1 for single item lots
+            quantity: u64,
+        }
+
+        /// Proxy bid placement, the chain bids on behalf of the owner up to the maximum
+        struct PlaceProxyBid {
+            /// Bid initiator
+            owner: &PublicKey,
+            /// ID (hash) of the lot to bid on
+            lot: &Hash,
+            /// Maximum bid amount, frozen while the proxy bid leads
+            max_amount: u64,
+        }
+
+        /// All-or-nothing bid for several lots
+        struct PlaceBundleBid {
+            /// Bid initiator
+            owner: &PublicKey,
+            /// IDs (hashes) of the lots to bid on
+            lots: &[Hash],
+            /// Amount offered for all lots together
+            amount: u64,
+            /// Auxiliary number to guarantee non-idempotence of transactions
+            seed: u64,
+        }
+
+        /// Retraction of the highest bid by its owner
+        struct RetractBid {
+            /// Bid initiator
+            owner: &PublicKey,
+            /// ID (hash) of the lot
+            lot: &Hash,
+            /// Auxiliary number to guarantee non-idempotence of transactions
+            seed: u64,
+        }
+
+        /// Commitment to a sealed bid
+        struct CommitBid {
+            /// Bid initiator
+            owner: &PublicKey,
+            /// ID (hash) of the lot to bid on
+            lot: &Hash,
+            /// Commitment to the bid amount computed with `schema::amount_commitment`
+            commitment: &Hash,
+            /// Amount frozen until the lot is settled, the revealed bid can't exceed it
+            deposit: u64,
+        }
+
+        /// Reveal of a sealed bid
+        struct RevealBid {
+            /// Bid initiator
+            owner: &PublicKey,
+            /// ID (hash) of the lot
+            lot: &Hash,
             /// Bid amount
             amount: u64,
+            /// Salt used for the commitment
+            salt: &Hash,
+        }
+
+        /// Instant purchase of a lot at its buy-now price
+        struct BuyNow {
+            /// Buyer
+            buyer: &PublicKey,
+            /// ID (hash) of the lot to buy
+            lot: &Hash,
+        }
+
+        /// Purchase of a Dutch auction lot at its current price
+        struct AcceptPrice {
+            /// Buyer
+            buyer: &PublicKey,
+            /// ID (hash) of the lot to buy
+            lot: &Hash,
+        }
+
+        /// Lot closing, transfers the winning bid to the lot owner
+        struct CloseLot {
+            /// Lot owner
+            owner: &PublicKey,
+            /// ID (hash) of the lot to close
+            lot: &Hash,
+            /// Revealed reserve price, ignored for lots without a reserve
+            reserve: u64,
+            /// Salt used for the reserve price commitment
+            salt: &Hash,
+        }
+
+        /// Creation of a fungible asset traded on an order book
+        struct CreateFungibleAsset {
+            /// Issuer receiving the whole supply
+            issuer: &PublicKey,
+            /// Asset name
+            name: &str,
+            /// Total amount of units
+            supply: u64,
+            /// Auxiliary number to guarantee non-idempotence of transactions
+            seed: u64,
+        }
+
+        /// Order placement, the order is matched against the asset's order book
+        struct PlaceOrder {
+            /// Order owner
+            owner: &PublicKey,
+            /// ID (hash) of the asset
+            asset: &Hash,
+            /// Order side, see `schema::OrderSide`
+            side: u8,
+            /// Limit price per unit
+            price: u64,
+            /// Amount of units
+            quantity: u64,
+            /// Auxiliary number to guarantee non-idempotence of transactions
+            seed: u64,
+        }
+
+        /// Cancellation of an order resting in the order book
+        struct CancelOrder {
+            /// Order owner
+            owner: &PublicKey,
+            /// ID (hash) of the order
+            order: &Hash,
+        }
+
+        /// Lot cancellation, releases the highest bid
+        struct CancelLot {
+            /// Lot owner
+            owner: &PublicKey,
+            /// ID (hash) of the lot to cancel
+            lot: &Hash,
+        }
+
+        /// Confirmation of a lot's delivery, releases the escrowed payment to the lot owner
+        struct ConfirmDelivery {
+            /// Buyer
+            buyer: &PublicKey,
+            /// ID (hash) of the delivered lot
+            lot: &Hash,
+        }
+
+        /// Dispute over the delivery of a lot in escrow, suspends the release of the payment
+        struct OpenDispute {
+            /// Buyer
+            buyer: &PublicKey,
+            /// ID (hash) of the disputed lot
+            lot: &Hash,
+            /// Reason of the dispute
+            comment: &str,
+        }
+
+        /// Resolution of a dispute splitting the escrowed payment between the buyer and the seller,
+        /// only allowed for the configured arbiters
+        struct ResolveDispute {
+            /// Arbiter
+            arbiter: &PublicKey,
+            /// ID (hash) of the disputed lot
+            lot: &Hash,
+            /// Amount refunded to the buyer
+            buyer_amount: u64,
+            /// Amount paid to the seller
+            seller_amount: u64,
+            /// Explanation of the ruling
+            comment: &str,
         }
     }
 }
@@ -105,7 +747,7 @@ impl Transaction for CreateWallet {
         let pub_key = self.pub_key();
 
         if schema.wallet(pub_key).is_none() {
-            schema.create_wallet(pub_key, self.name(), self.balance());
+            schema.create_wallet(pub_key, self.name());
             Ok(())
         } else {
             Err(Error::WalletAlreadyExists)?
@@ -113,25 +755,92 @@ impl Transaction for CreateWallet {
     }
 }
 
-impl Transaction for CreateLot {
+impl Transaction for RegisterCurrency {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.issuer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+
+        if !schema.config().issuers.contains(self.issuer()) {
+            Err(Error::NotIssuer)?
+        }
+
+        let currency = Currency::new(&self.hash(), self.name(), self.decimals(), self.issuer());
+        schema.register_currency(currency);
+        Ok(())
+    }
+}
+
+impl Transaction for Issue {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.issuer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+
+        let allowed = if *self.currency() == native_currency() {
+            schema.config().issuers.contains(self.issuer())
+        } else {
+            match schema.currency(self.currency()) {
+                Some(val) => val.issuer() == self.issuer(),
+                None => Err(Error::CurrencyNotFound)?,
+            }
+        };
+
+        if !allowed {
+            Err(Error::NotIssuer)?
+        }
+
+        schema.issue(self.to(), self.currency(), self.amount())
+    }
+}
+
+impl Transaction for Transfer {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.from())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+
+        if self.from() == self.to() {
+            Err(Error::SelfTransfer)?
+        }
+
+        if self.amount() == 0 {
+            Err(Error::ZeroTransferAmount)?
+        }
+
+        if !schema.currency_exists(self.currency()) {
+            Err(Error::CurrencyNotFound)?
+        }
+
+        schema.transfer(self.from(), self.to(), self.currency(), self.amount())
+    }
+}
+
+impl Transaction for IssueAsset {
     fn verify(&self) -> bool {
         self.verify_signature(self.owner())
     }
 
     fn execute(&self, fork: &mut Fork) -> ExecutionResult {
         let mut schema = Schema::new(fork);
-        let owner = self.owner();
 
-        if schema.wallet(owner).is_none() {
+        if schema.wallet(self.owner()).is_none() {
             Err(Error::WalletNotFound)?
-        } else {
-            schema.create_lot(owner, self.name(), self.min_bid(), &self.hash());
-            Ok(())
         }
+
+        let asset = Asset::new(&self.hash(), self.name(), self.owner(), &Hash::zero());
+        schema.issue_asset(asset);
+        Ok(())
     }
 }
 
-impl Transaction for PlaceBid {
+impl Transaction for CreateLot {
     fn verify(&self) -> bool {
         self.verify_signature(self.owner())
     }
@@ -139,19 +848,539 @@ impl Transaction for PlaceBid {
     fn execute(&self, fork: &mut Fork) -> ExecutionResult {
         let mut schema = Schema::new(fork);
         let owner = self.owner();
-        let lot = match schema.lot(self.lot()) {
+        let deadlines = self.deadlines();
+        let bidding = self.bidding();
+        let params = self.format();
+
+        let wallet = match schema.wallet(owner) {
             Some(val) => val,
-            None => Err(Error::LotNotFound)?,
+            None => Err(Error::WalletNotFound)?,
         };
 
-        if lot.min_bid() > self.amount() {
-            Err(Error::BidTooLow)?
+        if !schema.currency_exists(self.currency()) {
+            Err(Error::CurrencyNotFound)?
         }
 
-        if lot.owner() == owner {
-            Err(Error::BiddingNotAllowedOnOwnLot)?
+        if deadlines.end_height() != 0 && deadlines.end_height() <= schema.height().0 {
+            Err(Error::InvalidDeadline)?
+        }
+
+        let closes_at = if deadlines.closes_at().is_empty() {
+            no_time_deadline()
+        } else {
+            match DateTime::parse_from_rfc3339(deadlines.closes_at()) {
+                Ok(val) => val.with_timezone(&Utc),
+                Err(_) => Err(Error::InvalidDeadline)?,
+            }
+        };
+
+        if closes_at != no_time_deadline() && schema.time().map_or(false, |now| closes_at <= now) {
+            Err(Error::InvalidDeadline)?
+        }
+
+        if deadlines.extension_window() > MAX_EXTENSION || deadlines.extension() > MAX_EXTENSION {
+            Err(Error::ExtensionTooLong)?
+        }
+
+        if bidding.buy_now() != 0 && bidding.buy_now() < self.min_bid() {
+            Err(Error::InvalidBuyNowPrice)?
+        }
+
+        let format = match AuctionFormat::from_raw(params.format()) {
+            Some(val) => val,
+            None => Err(Error::UnsupportedAuctionFormat)?,
+        };
+
+        if format.is_sealed() {
+            if deadlines.end_height() == 0 || closes_at != no_time_deadline() {
+                Err(Error::InvalidDeadline)?
+            }
+            if params.reveal_period() == 0 || params.reveal_period() > MAX_REVEAL_PERIOD {
+                Err(Error::InvalidRevealPeriod)?
+            }
+            if deadlines.end_height().checked_add(params.reveal_period()).is_none() {
+                Err(Error::InvalidRevealPeriod)?
+            }
+        } else if params.reveal_period() != 0 {
+            Err(Error::InvalidRevealPeriod)?
+        }
+
+        if format != AuctionFormat::English && (bidding.buy_now() != 0 || bidding.retraction_window() != 0) {
+            Err(Error::UnsupportedAuctionFormat)?
+        }
+
+        if format == AuctionFormat::Dutch && params.start_price() < self.min_bid() {
+            Err(Error::InvalidStartPrice)?
+        }
+
+        if params.quantity() > 1 && (format != AuctionFormat::English || bidding.buy_now() != 0 || bidding.retraction_window() != 0) {
+            Err(Error::UnsupportedAuctionFormat)?
+        }
+
+        if (format == AuctionFormat::Reverse || format == AuctionFormat::Dutch) && *bidding.reserve_hash() != Hash::zero() {
+            Err(Error::UnsupportedAuctionFormat)?
+        }
+
+        // An escrow holds a single payment by the lot's buyer, reverse and multi-unit lots are
+        // paid to the bidders or by several of them
+        if (format == AuctionFormat::Reverse || params.quantity() > 1) && schema.config().escrow_period != 0 {
+            Err(Error::UnsupportedAuctionFormat)?
+        }
+
+        if *self.asset() != Hash::zero() {
+            if format == AuctionFormat::Reverse || params.quantity() > 1 {
+                Err(Error::UnsupportedAuctionFormat)?
+            }
+
+            let asset = match schema.asset(self.asset()) {
+                Some(val) => val,
+                None => Err(Error::AssetNotFound)?,
+            };
+
+            if asset.owner() != owner {
+                Err(Error::NotAssetOwner)?
+            }
+
+            if asset.is_locked() {
+                Err(Error::AssetLocked)?
+            }
+        }
+
+        let lot = Lot::new(
+            owner,
+            self.name(),
+            self.min_bid(),
+            &self.hash(),
+            LotStatus::Open as u8,
+            deadlines.end_height(),
+            closes_at,
+            deadlines.extension_window(),
+            deadlines.extension(),
+            bidding.reserve_hash(),
+            bidding.buy_now(),
+            bidding.min_increment(),
+            bidding.min_increment_percent(),
+            params.format(),
+            params.reveal_period(),
+            params.start_price(),
+            params.price_decrement(),
+            schema.height().0,
+            params.quantity(),
+            bidding.retraction_window(),
+            bidding.retraction_penalty(),
+            self.currency(),
+            self.asset(),
+        );
+
+        let config = schema.config();
+        if config.treasury.is_some() && wallet.available(&native_currency()) < config.listing_fee {
+            Err(Error::ListingFeeUnpaid)?
+        }
+
+        schema.pay_listing_fee(owner)?;
+        schema.create_lot(lot)
+    }
+}
+
+impl Transaction for PlaceBid {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let owner = self.owner();
+        let lot = open_lot(&schema, self.lot(), owner, &[AuctionFormat::English, AuctionFormat::Reverse])?;
+
+        if lot.has_format(AuctionFormat::Reverse) {
+            if self.amount() > lot.min_bid() {
+                Err(Error::BidTooHigh)?
+            }
+        } else if lot.min_bid() > self.amount() {
+            Err(Error::BidTooLow)?
+        }
+
+        if self.quantity() == 0 || self.quantity() > lot.units() {
+            Err(Error::InvalidQuantity)?
+        }
+
+        if lot.is_multi_unit() {
+            schema.place_multi_unit_bid(owner, lot.tx_hash(), self.amount(), self.quantity())
         } else {
             schema.place_bid(owner, lot.tx_hash(), self.amount())
         }
     }
 }
+
+impl Transaction for PlaceProxyBid {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let lot = open_lot(&schema, self.lot(), self.owner(), &[AuctionFormat::English])?;
+
+        if lot.is_multi_unit() {
+            Err(Error::UnsupportedAuctionFormat)?
+        }
+
+        if lot.min_bid() > self.max_amount() {
+            Err(Error::BidTooLow)?
+        }
+
+        schema.place_proxy_bid(self.owner(), lot.tx_hash(), self.max_amount())
+    }
+}
+
+impl Transaction for PlaceBundleBid {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let lots = self.lots();
+
+        if lots.len() < 2 || lots.len() > MAX_BUNDLE_LOTS {
+            Err(Error::InvalidBundle)?
+        }
+
+        if lots.iter().enumerate().any(|(i, lot)| lots[..i].contains(lot)) {
+            Err(Error::InvalidBundle)?
+        }
+
+        let mut deadline = None;
+        let mut standalone = 0u64;
+        for id in lots {
+            let lot = open_lot(&schema, id, self.owner(), &[AuctionFormat::English])?;
+            let key = (lot.end_height(), *lot.currency());
+
+            if !lot.accepts_bundle_bids() || deadline.map_or(false, |val| val != key) {
+                Err(Error::InvalidBundle)?
+            }
+
+            deadline = Some(key);
+            standalone = standalone.saturating_add(schema.standalone_price(&lot));
+        }
+
+        if self.amount() <= standalone {
+            Err(Error::BidTooLow)?
+        }
+
+        let end_height = Height(deadline.map_or(0, |(val, _)| val));
+        if schema.expiring_bundle_bids(end_height).len() >= MAX_BUNDLE_BIDS {
+            Err(Error::TooManyBundleBids)?
+        }
+
+        schema.place_bundle_bid(self.owner(), lots, self.amount(), &self.hash())
+    }
+}
+
+impl Transaction for RetractBid {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let lot = open_lot(&schema, self.lot(), self.owner(), &[AuctionFormat::English])?;
+
+        let bid = match schema.last_bid(lot.tx_hash()) {
+            Some(val) => val,
+            None => Err(Error::NotHighestBidder)?,
+        };
+
+        if bid.owner() != self.owner() {
+            Err(Error::NotHighestBidder)?
+        }
+
+        if lot.retraction_window() == 0 || schema.height().0 > bid.height().saturating_add(lot.retraction_window()) {
+            Err(Error::RetractionWindowOver)?
+        }
+
+        schema.retract_bid(lot.tx_hash())
+    }
+}
+
+impl Transaction for CommitBid {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let lot = sealed_bid_lot(&schema, self.lot())?;
+
+        if lot.owner() == self.owner() {
+            Err(Error::BiddingNotAllowedOnOwnLot)?
+        }
+
+        if schema.height().0 > lot.end_height() {
+            Err(Error::CommitPhaseOver)?
+        }
+
+        if lot.min_bid() > self.deposit() {
+            Err(Error::BidTooLow)?
+        }
+
+        schema.commit_bid(self.owner(), lot.tx_hash(), self.commitment(), self.deposit())
+    }
+}
+
+impl Transaction for RevealBid {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let lot = sealed_bid_lot(&schema, self.lot())?;
+        let height = schema.height().0;
+
+        if height <= lot.end_height() {
+            Err(Error::RevealPhaseNotStarted)?
+        }
+
+        if height > lot.settlement_height() {
+            Err(Error::RevealPhaseOver)?
+        }
+
+        if lot.min_bid() > self.amount() {
+            Err(Error::InvalidBidReveal)?
+        }
+
+        schema.reveal_bid(self.owner(), lot.tx_hash(), self.amount(), self.salt())
+    }
+}
+
+impl Transaction for BuyNow {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.buyer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let lot = open_lot(&schema, self.lot(), self.buyer(), &[AuctionFormat::English])?;
+
+        if lot.buy_now() == 0 {
+            Err(Error::BuyNowNotAvailable)?
+        } else {
+            schema.buy_now(self.buyer(), lot.tx_hash())
+        }
+    }
+}
+
+impl Transaction for AcceptPrice {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.buyer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let lot = open_lot(&schema, self.lot(), self.buyer(), &[AuctionFormat::Dutch])?;
+        schema.accept_price(self.buyer(), lot.tx_hash())
+    }
+}
+
+impl Transaction for CloseLot {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let lot = match schema.lot(self.lot()) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        if lot.owner() != self.owner() {
+            Err(Error::NotLotOwner)?
+        }
+
+        if lot.is_open() && lot.has_deadline() && !lot.is_finished(schema.height(), schema.time()) {
+            Err(Error::DeadlineNotReached)?
+        }
+
+        if !lot.has_reserve() {
+            schema.close_lot(lot.tx_hash(), 0)
+        } else if amount_commitment(self.reserve(), self.salt()) != *lot.reserve_hash() {
+            Err(Error::InvalidReserve)?
+        } else {
+            schema.close_lot(lot.tx_hash(), self.reserve())
+        }
+    }
+}
+
+impl Transaction for CancelLot {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let lot = match schema.lot(self.lot()) {
+            Some(val) => val,
+            None => Err(Error::LotNotFound)?,
+        };
+
+        if lot.owner() != self.owner() {
+            Err(Error::NotLotOwner)?
+        }
+
+        if lot.is_expired(schema.height(), schema.time()) {
+            Err(Error::LotExpired)?
+        } else {
+            schema.cancel_lot(lot.tx_hash())
+        }
+    }
+}
+
+impl Transaction for ConfirmDelivery {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.buyer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+
+        if schema.lot(self.lot()).is_none() {
+            Err(Error::LotNotFound)?
+        }
+
+        let escrow = match schema.escrow(self.lot()) {
+            Some(val) => val,
+            None => Err(Error::NotInEscrow)?,
+        };
+
+        if escrow.buyer() != self.buyer() {
+            Err(Error::NotBuyer)?
+        }
+
+        schema.release_escrow(self.lot())
+    }
+}
+
+impl Transaction for OpenDispute {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.buyer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+
+        if schema.lot(self.lot()).is_none() {
+            Err(Error::LotNotFound)?
+        }
+
+        let escrow = match schema.escrow(self.lot()) {
+            Some(val) => val,
+            None => Err(Error::NotInEscrow)?,
+        };
+
+        if escrow.buyer() != self.buyer() {
+            Err(Error::NotBuyer)?
+        }
+
+        let action = DisputeAction::Opened as u8;
+        let step = DisputeStep::new(self.buyer(), action, 0, 0, self.comment(), schema.height().0, &self.hash());
+        schema.open_dispute(self.lot(), step)
+    }
+}
+
+impl Transaction for ResolveDispute {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.arbiter())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+
+        if !schema.config().arbiters.contains(self.arbiter()) {
+            Err(Error::NotArbiter)?
+        }
+
+        if schema.lot(self.lot()).is_none() {
+            Err(Error::LotNotFound)?
+        }
+
+        let step = DisputeStep::new(
+            self.arbiter(),
+            DisputeAction::Resolved as u8,
+            self.buyer_amount(),
+            self.seller_amount(),
+            self.comment(),
+            schema.height().0,
+            &self.hash(),
+        );
+        schema.resolve_dispute(self.lot(), step)
+    }
+}
+
+impl Transaction for CreateFungibleAsset {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.issuer())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+
+        if schema.wallet(self.issuer()).is_none() {
+            Err(Error::WalletNotFound)?
+        }
+
+        let asset = FungibleAsset::new(&self.hash(), self.name(), self.issuer(), self.supply());
+        schema.create_fungible_asset(asset);
+        Ok(())
+    }
+}
+
+impl Transaction for PlaceOrder {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+
+        if schema.fungible_asset(self.asset()).is_none() {
+            Err(Error::AssetNotFound)?
+        }
+
+        if schema.wallet(self.owner()).is_none() {
+            Err(Error::WalletNotFound)?
+        }
+
+        let side = match OrderSide::from_raw(self.side()) {
+            Some(val) => val,
+            None => Err(Error::InvalidOrder)?,
+        };
+
+        if self.price() == 0 || self.quantity() == 0 {
+            Err(Error::InvalidOrder)?
+        }
+
+        schema.place_order(self.owner(), self.asset(), side, self.price(), self.quantity(), &self.hash())
+    }
+}
+
+impl Transaction for CancelOrder {
+    fn verify(&self) -> bool {
+        self.verify_signature(self.owner())
+    }
+
+    fn execute(&self, fork: &mut Fork) -> ExecutionResult {
+        let mut schema = Schema::new(fork);
+        let order = match schema.order(self.order()) {
+            Some(val) => val,
+            None => Err(Error::OrderNotFound)?,
+        };
+
+        if order.owner() != self.owner() {
+            Err(Error::NotOrderOwner)?
+        }
+
+        schema.cancel_order(&order)
+    }
+}
@@ -10,7 +10,7 @@ extern crate serde_json;
 
 use exonum::{
     api::{node::public::explorer::TransactionQuery},
-    crypto::{self, CryptoHash, Hash, hash, PublicKey, SecretKey},
+    crypto::{self, CryptoHash, Hash, hash, PublicKey, SecretKey}, helpers::Height,
 };
 use exonum_testkit::{ApiKind, TestKit, TestKitApi, TestKitBuilder};
 use std::{thread, time};
@@ -20,10 +20,16 @@ mod common;
 const BLOCK_DELAY_SEC: u64 = 1;
 
 // Import data types used in tests from the crate where the service is defined.
-use auction::api::{WalletQuery, BidHistoryQuery, BidHistory};
-use auction::schema::Wallet;
-use auction::tx::{CreateWallet, CreateLot, PlaceBid};
-use common::{PHOBOS, DEIMOS, MIN_BID, BALANCE};
+use auction::api::{
+    AssetQuery, WalletQuery, BidHistoryQuery, BidHistory, LotQuery, LotPrice, CurrencyQuery, MarketProofQuery, Supply, Treasury,
+};
+use auction::schema::{native_currency, Asset, Currency, LotStatus, OrderSide, Wallet};
+use auction::tx::{
+    CreateWallet, RegisterCurrency, Issue, IssueAsset, CreateLot, PlaceBid, CloseLot, CancelLot, OpenDispute, ResolveDispute,
+    CreateFungibleAsset, PlaceOrder,
+};
+use auction::{AuctionConfig, Service};
+use common::{arbiter_keys, config, issuer_keys, service, treasury_keys, LotBuilder, PHOBOS, DEIMOS, MIN_BID, BALANCE};
 
 /// Check that the wallet creation transaction works when invoked via API.
 #[test]
@@ -36,7 +42,55 @@ fn test_api_create_wallet() {
     let wallet = api.wallet(*tx.pub_key());
     assert_eq!(wallet.pub_key(), tx.pub_key());
     assert_eq!(wallet.name(), tx.name());
-    assert_eq!(wallet.balance(), BALANCE);
+    assert_eq!(wallet.available(&native_currency()), 0);
+}
+
+/// Check that funds can be issued to a wallet and the total supply is updated.
+#[test]
+fn test_api_issue() {
+    let (mut testkit, api) = create_testkit();
+    let (tx, _) = api.create_wallet(PHOBOS);
+    let itx = api.issue(tx.pub_key(), BALANCE);
+    testkit.create_block_with_tx_hashes(&[tx.hash(), itx.hash()]);
+    api.assert_tx_status(itx.hash(), &json!({ "type": "success" }));
+
+    assert_eq!(api.wallet(*tx.pub_key()).available(&native_currency()), BALANCE);
+    assert_eq!(api.supply(native_currency()).total, BALANCE);
+}
+
+/// Check that a registered currency is listed and can be issued.
+#[test]
+fn test_api_register_currency() {
+    let (mut testkit, api) = create_testkit();
+    let (issuer, issuer_key) = issuer_keys();
+    let ctx = RegisterCurrency::new(&issuer, "gold", 2, 0, &issuer_key);
+    api.post_currency(&ctx);
+    testkit.create_block();
+    api.assert_tx_status(ctx.hash(), &json!({ "type": "success" }));
+
+    let currencies = api.currencies();
+    assert_eq!(currencies.len(), 1);
+    assert_eq!(currencies[0].id(), &ctx.hash());
+    assert_eq!(currencies[0].name(), "gold");
+    assert_eq!(api.supply(ctx.hash()).total, 0);
+}
+
+/// Check that an issued asset can be retrieved.
+#[test]
+fn test_api_issue_asset() {
+    let (mut testkit, api) = create_testkit();
+    let (tx, key) = api.create_wallet(PHOBOS);
+    testkit.create_block();
+
+    let atx = IssueAsset::new(&tx.pub_key(), "painting", 0, &key);
+    api.post_asset(&atx);
+    testkit.create_block();
+    api.assert_tx_status(atx.hash(), &json!({ "type": "success" }));
+
+    let asset = api.asset(atx.hash());
+    assert_eq!(asset.owner(), tx.pub_key());
+    assert_eq!(asset.name(), "painting");
+    assert!(!asset.is_locked());
 }
 
 /// Test lot creation
@@ -57,7 +111,8 @@ fn test_api_place_bid() {
     let (tx, key) = api.create_wallet(PHOBOS);
     let ltx = api.create_lot(&tx.pub_key(), &key);
     let (bidder_tx, key) = api.create_wallet(DEIMOS);
-    testkit.create_block_with_tx_hashes(&[tx.hash(), ltx.hash(), bidder_tx.hash()]);
+    let itx = api.issue(bidder_tx.pub_key(), BALANCE);
+    testkit.create_block_with_tx_hashes(&[tx.hash(), ltx.hash(), bidder_tx.hash(), itx.hash()]);
 
     thread::spawn(move || {
         thread::sleep(time::Duration::from_secs(BLOCK_DELAY_SEC));
@@ -68,7 +123,9 @@ fn test_api_place_bid() {
     api.assert_tx_status(btx.hash(), &json!({ "type": "success" }));
 
     let bid_history = api.bid_history(ltx.hash());
+    assert_eq!(bid_history.status, Some(LotStatus::Open));
     assert_eq!(bid_history.bids.len(), 1);
+    assert!(bid_history.extensions.is_empty());
 
     let last_bid = bid_history.bids.last().unwrap();
     assert_eq!(last_bid.owner(), bidder_tx.pub_key());
@@ -81,7 +138,7 @@ fn test_api_create_existing_wallet() {
     let (tx, key) = api.create_wallet(PHOBOS);
     testkit.create_block();
 
-    let dup_tx = CreateWallet::new(&tx.pub_key(), "duplicate wallet", BALANCE, &key);
+    let dup_tx = CreateWallet::new(&tx.pub_key(), "duplicate wallet", &key);
     let _tx_info: serde_json::Value = api.inner
         .public(ApiKind::Service(auction::SERVICE_NAME))
         .query(&dup_tx)
@@ -165,13 +222,42 @@ fn test_api_place_bid_below_minimum() {
     );
 }
 
+#[test]
+fn test_api_place_bid_increment_too_small() {
+    let (mut testkit, api) = create_testkit();
+    let (tx, key) = api.create_wallet(PHOBOS);
+    let (bidder_tx, bidder_key) = api.create_wallet(DEIMOS);
+    let itx = api.issue(bidder_tx.pub_key(), BALANCE);
+    testkit.create_block_with_tx_hashes(&[tx.hash(), bidder_tx.hash(), itx.hash()]);
+
+    let ltx = LotBuilder::new().name("Test lot").increment(5, 0).sign(&tx.pub_key(), &key);
+    testkit.create_block_with_transaction(ltx.clone());
+    testkit.create_block_with_transaction(PlaceBid::new(&bidder_tx.pub_key(), &ltx.hash(), MIN_BID, 1, &bidder_key));
+
+    thread::spawn(move || {
+        thread::sleep(time::Duration::from_secs(BLOCK_DELAY_SEC));
+        testkit.create_block();
+    });
+    let btx = api.place_bid(&bidder_tx.pub_key(), &ltx.hash(), MIN_BID + 1, &bidder_key, 4);
+
+    api.assert_tx_status(
+        btx.hash(),
+        &json!({
+            "type": "error",
+            "code": 17,
+            "description": format!("Bid increment too small, next acceptable amount is {}", MIN_BID + 5),
+        }),
+    );
+}
+
 #[test]
 fn test_api_place_bid_above_balance() {
     let (mut testkit, api) = create_testkit();
     let (tx, key) = api.create_wallet(PHOBOS);
     let ltx = api.create_lot(&tx.pub_key(), &key);
     let (bidder_tx, key) = api.create_wallet(DEIMOS);
-    testkit.create_block_with_tx_hashes(&[tx.hash(), ltx.hash(), bidder_tx.hash()]);
+    let itx = api.issue(bidder_tx.pub_key(), BALANCE);
+    testkit.create_block_with_tx_hashes(&[tx.hash(), ltx.hash(), bidder_tx.hash(), itx.hash()]);
 
     thread::spawn(move || {
         thread::sleep(time::Duration::from_secs(BLOCK_DELAY_SEC));
@@ -181,10 +267,155 @@ fn test_api_place_bid_above_balance() {
 
     api.assert_tx_status(
         btx.hash(),
-        &json!({ "type": "error", "code": 3, "description": "Currency amount insufficient for bid placement" }),
+        &json!({ "type": "error", "code": 3, "description": "Available currency amount insufficient for the operation" }),
+    );
+}
+
+#[test]
+fn test_api_close_lot_by_non_owner() {
+    let (mut testkit, api) = create_testkit();
+    let (tx, key) = api.create_wallet(PHOBOS);
+    let ltx = api.create_lot(&tx.pub_key(), &key);
+    let (bidder_tx, bidder_key) = api.create_wallet(DEIMOS);
+    testkit.create_block_with_tx_hashes(&[tx.hash(), ltx.hash(), bidder_tx.hash()]);
+
+    let ctx = api.close_lot(&bidder_tx.pub_key(), &ltx.hash(), &bidder_key);
+    testkit.create_block();
+    api.assert_tx_status(
+        ctx.hash(),
+        &json!({ "type": "error", "code": 7, "description": "Operation allowed only for the lot owner" }),
     );
 }
 
+#[test]
+fn test_api_cancel_lot() {
+    let (mut testkit, api) = create_testkit();
+    let (tx, key) = api.create_wallet(PHOBOS);
+    let ltx = api.create_lot(&tx.pub_key(), &key);
+    testkit.create_block_with_tx_hashes(&[tx.hash(), ltx.hash()]);
+
+    let ctx = CancelLot::new(&tx.pub_key(), &ltx.hash(), &key);
+    let tx_info: serde_json::Value = api.inner
+        .public(ApiKind::Service(auction::SERVICE_NAME))
+        .query(&ctx)
+        .post("v1/lots/cancel")
+        .unwrap();
+    assert_eq!(tx_info, json!({ "tx_hash": ctx.hash() }));
+    testkit.create_block();
+    api.assert_tx_status(ctx.hash(), &json!({ "type": "success" }));
+
+    let bid_history = api.bid_history(ltx.hash());
+    assert_eq!(bid_history.status, Some(LotStatus::Cancelled));
+}
+
+#[test]
+fn test_api_dutch_lot_price() {
+    let (mut testkit, api) = create_testkit();
+    let (tx, key) = api.create_wallet(PHOBOS);
+    let ltx = LotBuilder::new().name("Test lot").dutch(50, 5).sign(&tx.pub_key(), &key);
+    testkit.create_block_with_tx_hashes(&[tx.hash()]);
+    testkit.create_block_with_transaction(ltx.clone());
+
+    let price = api.lot_price(ltx.hash());
+    assert_eq!(price.height, Height(3));
+    assert_eq!(price.price, 45);
+
+    testkit.create_blocks_until(Height(4));
+    let price = api.lot_price(ltx.hash());
+    assert_eq!(price.height, Height(5));
+    assert_eq!(price.price, 35);
+}
+
+#[test]
+fn test_api_dispute() {
+    let (mut testkit, api) = create_testkit_with_config(AuctionConfig { escrow_period: 5, ..config() });
+    let (tx_owner, owner_key) = api.create_wallet(PHOBOS);
+    let (tx_buyer, buyer_key) = api.create_wallet(DEIMOS);
+    let ltx = api.create_lot(&tx_owner.pub_key(), &owner_key);
+    let itx = api.issue(tx_buyer.pub_key(), BALANCE);
+    testkit.create_block_with_tx_hashes(&[tx_owner.hash(), tx_buyer.hash(), ltx.hash(), itx.hash()]);
+    testkit.create_block_with_transaction(PlaceBid::new(&tx_buyer.pub_key(), &ltx.hash(), 20, 1, &buyer_key));
+    testkit.create_block_with_transaction(CloseLot::new(&tx_owner.pub_key(), &ltx.hash(), 0, &Hash::zero(), &owner_key));
+    assert!(api.bid_history(ltx.hash()).escrow.is_some());
+
+    let otx = OpenDispute::new(&tx_buyer.pub_key(), &ltx.hash(), "damaged", &buyer_key);
+    let tx_info: serde_json::Value = api.inner
+        .public(ApiKind::Service(auction::SERVICE_NAME))
+        .query(&otx)
+        .post("v1/disputes")
+        .unwrap();
+    assert_eq!(tx_info, json!({ "tx_hash": otx.hash() }));
+    testkit.create_block();
+    api.assert_tx_status(otx.hash(), &json!({ "type": "success" }));
+    assert_eq!(api.bid_history(ltx.hash()).status, Some(LotStatus::Disputed));
+
+    let (arbiter, arbiter_key) = arbiter_keys();
+    let rtx = ResolveDispute::new(&arbiter, &ltx.hash(), 5, 15, "partially damaged", &arbiter_key);
+    let tx_info: serde_json::Value = api.inner
+        .public(ApiKind::Service(auction::SERVICE_NAME))
+        .query(&rtx)
+        .post("v1/disputes/resolve")
+        .unwrap();
+    assert_eq!(tx_info, json!({ "tx_hash": rtx.hash() }));
+    testkit.create_block();
+    api.assert_tx_status(rtx.hash(), &json!({ "type": "success" }));
+    assert_eq!(api.bid_history(ltx.hash()).status, Some(LotStatus::Closed));
+
+    let proof = api.dispute(ltx.hash());
+    assert!(proof["block_proof"].is_object());
+    assert!(proof["to_table"].is_object());
+    assert!(proof["to_dispute"].is_object());
+    assert!(!proof["history"].is_null());
+}
+
+#[test]
+fn test_api_market_proof() {
+    let (mut testkit, api) = create_testkit();
+    let (tx_seller, seller_key) = api.create_wallet(PHOBOS);
+    let (tx_buyer, buyer_key) = api.create_wallet(DEIMOS);
+    let itx = api.issue(tx_buyer.pub_key(), BALANCE);
+    testkit.create_block_with_tx_hashes(&[tx_seller.hash(), tx_buyer.hash(), itx.hash()]);
+
+    let atx = CreateFungibleAsset::new(&tx_seller.pub_key(), "test asset", 100, 0, &seller_key);
+    testkit.create_block_with_transaction(atx.clone());
+    let proof = api.market_proof(atx.hash(), *tx_seller.pub_key());
+    assert!(proof["to_ledger"].is_object());
+    assert!(proof["trades"].is_null());
+
+    let buy = OrderSide::Buy as u8;
+    testkit.create_block_with_transaction(PlaceOrder::new(&tx_buyer.pub_key(), &atx.hash(), buy, 5, 2, 0, &buyer_key));
+    let sell = OrderSide::Sell as u8;
+    testkit.create_block_with_transaction(PlaceOrder::new(&tx_seller.pub_key(), &atx.hash(), sell, 5, 2, 0, &seller_key));
+
+    let proof = api.market_proof(atx.hash(), *tx_buyer.pub_key());
+    assert!(proof["block_proof"].is_object());
+    assert!(proof["to_table"].is_object());
+    assert!(proof["to_ledger"].is_object());
+    assert!(proof["holdings"].is_object());
+    assert!(!proof["trades"].is_null());
+}
+
+#[test]
+fn test_api_treasury() {
+    let config = AuctionConfig { treasury: Some(treasury_keys().0), listing_fee: 5, ..config() };
+    let (mut testkit, api) = create_testkit_with_config(config);
+    let treasury = api.treasury();
+    assert_eq!(treasury.pub_key, Some(treasury_keys().0));
+    assert!(treasury.totals.is_empty());
+
+    let (tx, key) = api.create_wallet(PHOBOS);
+    let itx = api.issue(tx.pub_key(), BALANCE);
+    let ltx = api.create_lot(&tx.pub_key(), &key);
+    testkit.create_block_with_tx_hashes(&[tx.hash(), itx.hash(), ltx.hash()]);
+    api.assert_tx_status(ltx.hash(), &json!({ "type": "success" }));
+
+    let treasury = api.treasury();
+    assert_eq!(treasury.totals.len(), 1);
+    assert_eq!(treasury.totals[0].currency, native_currency());
+    assert_eq!(treasury.totals[0].total, 5);
+    assert_eq!(api.wallet(treasury_keys().0).available(&native_currency()), 5);
+}
+
 struct ApiWrapper {
     pub inner: TestKitApi,
 }
@@ -193,7 +424,7 @@ impl ApiWrapper {
     fn create_wallet(&self, name: &str) -> (CreateWallet, SecretKey) {
         let (pubkey, key) = crypto::gen_keypair();
         // Create a pre-signed transaction
-        let tx = CreateWallet::new(&pubkey, name, BALANCE, &key);
+        let tx = CreateWallet::new(&pubkey, name, &key);
 
         let tx_info: serde_json::Value = self.inner
             .public(ApiKind::Service(auction::SERVICE_NAME))
@@ -204,6 +435,92 @@ impl ApiWrapper {
         (tx, key)
     }
 
+    /// Issues funds to a wallet on behalf of the test issuer.
+    fn issue(&self, to: &PublicKey, amount: u64) -> Issue {
+        let (issuer, issuer_key) = issuer_keys();
+        let tx = Issue::new(&issuer, to, &native_currency(), amount, 0, &issuer_key);
+
+        let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .query(&tx)
+            .post("v1/wallets/issue")
+            .unwrap();
+        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
+        tx
+    }
+
+    /// Posts a currency registration transaction.
+    fn post_currency(&self, tx: &RegisterCurrency) {
+        let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .query(tx)
+            .post("v1/currencies")
+            .unwrap();
+        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
+    }
+
+    /// Posts an asset issuance transaction.
+    fn post_asset(&self, tx: &IssueAsset) {
+        let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .query(tx)
+            .post("v1/assets")
+            .unwrap();
+        assert_eq!(tx_info, json!({ "tx_hash": tx.hash() }));
+    }
+
+    /// Gets the steps of a lot's dispute with their proof.
+    fn dispute(&self, lot_id: Hash) -> serde_json::Value {
+        self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .query(&LotQuery { id: lot_id })
+            .get("v1/disputes")
+            .unwrap()
+    }
+
+    /// Gets a wallet's holdings and the trades of a fungible asset with their proof.
+    fn market_proof(&self, asset: Hash, owner: PublicKey) -> serde_json::Value {
+        self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .query(&MarketProofQuery { asset, owner })
+            .get("v1/market/proof")
+            .unwrap()
+    }
+
+    /// Gets a unique asset.
+    fn asset(&self, id: Hash) -> Asset {
+        self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .query(&AssetQuery { id })
+            .get("v1/assets")
+            .unwrap()
+    }
+
+    /// Gets the funds collected by the treasury.
+    fn treasury(&self) -> Treasury {
+        self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .get("v1/treasury")
+            .unwrap()
+    }
+
+    /// Gets registered currencies.
+    fn currencies(&self) -> Vec<Currency> {
+        self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .get("v1/currencies")
+            .unwrap()
+    }
+
+    /// Gets the total supply of a currency.
+    fn supply(&self, currency: Hash) -> Supply {
+        self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .query(&CurrencyQuery { currency })
+            .get("v1/supply")
+            .unwrap()
+    }
+
     /// Gets the state of a particular wallet using an HTTP request.
     fn wallet(&self, pub_key: PublicKey) -> Wallet {
         self.inner
@@ -221,6 +538,15 @@ impl ApiWrapper {
             .unwrap()
     }
 
+    /// Gets the current price of a Dutch auction lot.
+    fn lot_price(&self, lot_id: Hash) -> LotPrice {
+        self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .query(&LotQuery { id: lot_id })
+            .get("v1/lots/price")
+            .unwrap()
+    }
+
     /// Asserts that the transaction with the given hash has a specified status.
     fn assert_tx_status(&self, tx_hash: Hash, expected_status: &serde_json::Value) {
         let info: serde_json::Value = self.inner
@@ -239,7 +565,7 @@ impl ApiWrapper {
 
     /// Creates a lot given a participant's public key
     fn create_lot(&self, owner: &PublicKey, key: &SecretKey) -> CreateLot {
-        let ltx = CreateLot::new(owner, "Test lot", MIN_BID, key);
+        let ltx = LotBuilder::new().name("Test lot").sign(owner, key);
 
         let tx_info: serde_json::Value = self.inner
             .public(ApiKind::Service(auction::SERVICE_NAME))
@@ -250,8 +576,21 @@ impl ApiWrapper {
         ltx
     }
 
+    /// Closes a lot on behalf of the given participant
+    fn close_lot(&self, owner: &PublicKey, lot_id: &Hash, key: &SecretKey) -> CloseLot {
+        let ctx = CloseLot::new(owner, lot_id, 0, &Hash::zero(), key);
+
+        let tx_info: serde_json::Value = self.inner
+            .public(ApiKind::Service(auction::SERVICE_NAME))
+            .query(&ctx)
+            .post("v1/lots/close")
+            .unwrap();
+        assert_eq!(tx_info, json!({ "tx_hash": ctx.hash() }));
+        ctx
+    }
+
     fn place_bid(&self, bidder: &PublicKey, lot_id: &Hash, bid: u64, key: &SecretKey, expected_height: u64) -> PlaceBid {
-        let btx = PlaceBid::new(bidder, lot_id, bid, key);
+        let btx = PlaceBid::new(bidder, lot_id, bid, 1, key);
         let tx_info: serde_json::Value = self.inner
             .public(ApiKind::Service(auction::SERVICE_NAME))
             .query(&btx)
@@ -264,8 +603,17 @@ impl ApiWrapper {
 
 /// Creates a testkit together with the API wrapper defined above.
 fn create_testkit() -> (TestKit, ApiWrapper) {
+    create_testkit_with_service(service())
+}
+
+/// Creates a testkit with the service using the given configuration together with the API wrapper.
+fn create_testkit_with_config(config: AuctionConfig) -> (TestKit, ApiWrapper) {
+    create_testkit_with_service(Service::new(config))
+}
+
+fn create_testkit_with_service(service: Service) -> (TestKit, ApiWrapper) {
     let testkit = TestKitBuilder::validator()
-        .with_service(auction::Service)
+        .with_service(service)
         .create();
     let api = ApiWrapper {
         inner: testkit.api(),
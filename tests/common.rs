@@ -1,5 +0,0 @@
-//! Common definitions for api and transaction tests
-pub const PHOBOS: &str = "Phobos";
-pub const DEIMOS: &str = "Deimos";
-pub const BALANCE: u64 = 100;
-pub const MIN_BID: u64 = 10;
@@ -0,0 +1,201 @@
+//! Common definitions for api and transaction tests
+use auction::{
+    AuctionConfig, Service,
+    schema::{amount_commitment, native_currency, AuctionFormat},
+    tx::{BiddingOptions, CreateLot, DeadlineOptions, FormatOptions},
+};
+use exonum::crypto::{self, Hash, PublicKey, SecretKey, Seed};
+
+pub const PHOBOS: &str = "Phobos";
+pub const DEIMOS: &str = "Deimos";
+pub const BALANCE: u64 = 100;
+pub const MIN_BID: u64 = 10;
+
+/// Returns the keys of the issuer configured for the service in tests.
+pub fn issuer_keys() -> (PublicKey, SecretKey) {
+    crypto::gen_keypair_from_seed(&Seed::new([1; 32]))
+}
+
+/// Returns the keys of the arbiter configured for the service in tests.
+pub fn arbiter_keys() -> (PublicKey, SecretKey) {
+    crypto::gen_keypair_from_seed(&Seed::new([2; 32]))
+}
+
+/// Returns the keys of the treasury used in the fee tests.
+pub fn treasury_keys() -> (PublicKey, SecretKey) {
+    crypto::gen_keypair_from_seed(&Seed::new([3; 32]))
+}
+
+/// Returns the service configuration used in tests, with the test issuer and arbiter configured.
+pub fn config() -> AuctionConfig {
+    AuctionConfig {
+        issuers: vec![issuer_keys().0],
+        escrow_period: 0,
+        arbiters: vec![arbiter_keys().0],
+        treasury: None,
+        listing_fee: 0,
+        commission_percent: 0,
+    }
+}
+
+/// Creates the service with the test configuration.
+pub fn service() -> Service {
+    Service::new(config())
+}
+
+/// Builder of lot creation transactions, by default an English lot in the native currency
+/// with the minimum bid of `MIN_BID` and no deadline.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct LotBuilder {
+    name: String,
+    min_bid: u64,
+    end_height: u64,
+    closes_at: String,
+    extension_window: u64,
+    extension: u64,
+    reserve_hash: Hash,
+    buy_now: u64,
+    min_increment: u64,
+    min_increment_percent: u64,
+    retraction_window: u64,
+    retraction_penalty: u64,
+    format: AuctionFormat,
+    reveal_period: u64,
+    start_price: u64,
+    price_decrement: u64,
+    quantity: u64,
+    currency: Hash,
+    asset: Hash,
+}
+
+impl Default for LotBuilder {
+    fn default() -> Self {
+        LotBuilder::new()
+    }
+}
+
+#[allow(dead_code)]
+impl LotBuilder {
+    pub fn new() -> Self {
+        LotBuilder {
+            name: "test".to_owned(),
+            min_bid: MIN_BID,
+            end_height: 0,
+            closes_at: String::new(),
+            extension_window: 0,
+            extension: 0,
+            reserve_hash: Hash::zero(),
+            buy_now: 0,
+            min_increment: 0,
+            min_increment_percent: 0,
+            retraction_window: 0,
+            retraction_penalty: 0,
+            format: AuctionFormat::English,
+            reveal_period: 0,
+            start_price: 0,
+            price_decrement: 0,
+            quantity: 0,
+            currency: native_currency(),
+            asset: Hash::zero(),
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    pub fn min_bid(mut self, min_bid: u64) -> Self {
+        self.min_bid = min_bid;
+        self
+    }
+
+    pub fn end_height(mut self, end_height: u64) -> Self {
+        self.end_height = end_height;
+        self
+    }
+
+    pub fn closes_at(mut self, closes_at: &str) -> Self {
+        self.closes_at = closes_at.to_owned();
+        self
+    }
+
+    /// Extends the deadline by `extension` blocks on bids placed within `window` blocks of it.
+    pub fn extension(mut self, window: u64, extension: u64) -> Self {
+        self.extension_window = window;
+        self.extension = extension;
+        self
+    }
+
+    /// Hides the reserve price behind a commitment with the given salt.
+    pub fn reserve(mut self, reserve: u64, salt: &Hash) -> Self {
+        self.reserve_hash = amount_commitment(reserve, salt);
+        self
+    }
+
+    pub fn buy_now(mut self, buy_now: u64) -> Self {
+        self.buy_now = buy_now;
+        self
+    }
+
+    pub fn increment(mut self, min_increment: u64, min_increment_percent: u64) -> Self {
+        self.min_increment = min_increment;
+        self.min_increment_percent = min_increment_percent;
+        self
+    }
+
+    pub fn retraction(mut self, window: u64, penalty: u64) -> Self {
+        self.retraction_window = window;
+        self.retraction_penalty = penalty;
+        self
+    }
+
+    pub fn format(mut self, format: AuctionFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn reveal_period(mut self, reveal_period: u64) -> Self {
+        self.reveal_period = reveal_period;
+        self
+    }
+
+    /// Makes the lot a Dutch auction starting at `start_price` and dropping by `price_decrement` each block.
+    pub fn dutch(mut self, start_price: u64, price_decrement: u64) -> Self {
+        self.format = AuctionFormat::Dutch;
+        self.start_price = start_price;
+        self.price_decrement = price_decrement;
+        self
+    }
+
+    pub fn quantity(mut self, quantity: u64) -> Self {
+        self.quantity = quantity;
+        self
+    }
+
+    pub fn currency(mut self, currency: &Hash) -> Self {
+        self.currency = *currency;
+        self
+    }
+
+    pub fn asset(mut self, asset: &Hash) -> Self {
+        self.asset = *asset;
+        self
+    }
+
+    /// Creates the transaction signed by the lot owner.
+    pub fn sign(&self, owner: &PublicKey, key: &SecretKey) -> CreateLot {
+        let deadlines = DeadlineOptions::new(self.end_height, &self.closes_at, self.extension_window, self.extension);
+        let bidding = BiddingOptions::new(
+            &self.reserve_hash,
+            self.buy_now,
+            self.min_increment,
+            self.min_increment_percent,
+            self.retraction_window,
+            self.retraction_penalty,
+        );
+        let format = FormatOptions::new(self.format as u8, self.reveal_period, self.start_price, self.price_decrement, self.quantity);
+        CreateLot::new(owner, &self.name, self.min_bid, deadlines, bidding, format, &self.currency, &self.asset, key)
+    }
+}
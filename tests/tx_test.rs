@@ -1,22 +1,43 @@
 //! Unit tests
+extern crate chrono;
 extern crate exonum;
 //extern crate exonum_crypto;
 extern crate exonum_auction as auction;
 extern crate exonum_testkit;
+extern crate exonum_time;
 extern crate rand;
+extern crate serde_json;
 
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use exonum::{
-    crypto::{self, PublicKey, SecretKey, Hash, hash, CryptoHash},
+    blockchain::{self, Transaction, TransactionErrorType}, crypto::{self, PublicKey, SecretKey, Hash, hash, CryptoHash}, helpers::Height,
 };
 use exonum_testkit::{TestKit, TestKitBuilder};
+use exonum_time::{time_provider::MockTimeProvider, TimeService};
 
 // Import data types used in tests from the crate where the service is defined.
 use auction::{
-    schema::{Schema, Wallet, Lot, Bid}, tx::{CreateWallet, CreateLot, PlaceBid},
+    AuctionConfig, Service,
+    config::{MAX_COMMISSION_PERCENT, MAX_ESCROW_PERIOD},
+    schema::{
+        amount_commitment, native_currency, Asset, AuctionFormat, OrderSide, Schema, Wallet, Lot, Bid, Extension, SealedBid, Trade,
+        ASSET_LEDGERS_TABLE_INDEX, MAX_EXTENSION, MAX_REVEAL_PERIOD, RESERVE_REVEAL_PERIOD,
+    },
+    tx::{
+        CreateWallet, RegisterCurrency, Issue, Transfer, IssueAsset, CreateLot, PlaceBid, PlaceProxyBid, PlaceBundleBid, RetractBid, CommitBid, RevealBid, BuyNow, AcceptPrice, CloseLot, CancelLot,
+        ConfirmDelivery, OpenDispute, ResolveDispute, CreateFungibleAsset, PlaceOrder, CancelOrder, Error,
+    },
 };
 
 mod common;
-use common::{PHOBOS, DEIMOS, MIN_BID, BALANCE};
+use common::{arbiter_keys, config, issuer_keys, service, treasury_keys, LotBuilder, PHOBOS, DEIMOS, MIN_BID, BALANCE};
+
+/// Amount of blocks payments are held in escrow in the escrow tests.
+const ESCROW_PERIOD: u64 = 5;
+/// Listing fee charged in the fee tests.
+const LISTING_FEE: u64 = 5;
+/// Commission charged in the fee tests.
+const COMMISSION_PERCENT: u64 = 10;
 
 #[test]
 fn test_tx_create_wallet() {
@@ -27,14 +48,480 @@ fn test_tx_create_wallet() {
     let wallet = get_wallet(&testkit, tx.pub_key());
     assert_eq!(wallet.pub_key(), tx.pub_key());
     assert_eq!(wallet.name(), PHOBOS);
-    assert_eq!(wallet.balance(), BALANCE);
+    assert_eq!(wallet.available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_issue() {
+    let mut testkit = init_testkit();
+    let (tx, _) = create_wallet(&mut testkit, PHOBOS, 0);
+    assert_eq!(get_wallet(&testkit, tx.pub_key()).available(&native_currency()), 0);
+
+    let (issuer, issuer_key) = issuer_keys();
+    create_block_with_transaction(&mut testkit, Issue::new(&issuer, &tx.pub_key(), &native_currency(), BALANCE, 1, &issuer_key));
+
+    assert_eq!(get_wallet(&testkit, tx.pub_key()).available(&native_currency()), BALANCE);
+    assert_eq!(Schema::new(&testkit.snapshot()).total_issuance(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_issue_by_non_issuer() {
+    let mut testkit = init_testkit();
+    let (tx, key) = create_wallet(&mut testkit, PHOBOS, 0);
+
+    create_block_with_transaction(&mut testkit, Issue::new(&tx.pub_key(), &tx.pub_key(), &native_currency(), BALANCE, 0, &key));
+
+    assert_eq!(get_wallet(&testkit, tx.pub_key()).available(&native_currency()), 0);
+    assert_eq!(Schema::new(&testkit.snapshot()).total_issuance(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_transfer() {
+    let mut testkit = init_testkit();
+    let (tx_sender, sender_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let (tx_receiver, _) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _ttx = transfer(&mut testkit, &tx_sender.pub_key(), &sender_key, &tx_receiver.pub_key(), 30);
+
+    assert_eq!(get_wallet(&testkit, tx_sender.pub_key()).available(&native_currency()), BALANCE - 30);
+    assert_eq!(get_wallet(&testkit, tx_receiver.pub_key()).available(&native_currency()), BALANCE + 30);
+}
+
+#[test]
+fn test_tx_transfer_of_frozen_funds() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new());
+    let (tx_sender, sender_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_sender.pub_key(), &sender_key, &ltx.hash(), 90);
+    let _ttx = transfer(&mut testkit, &tx_sender.pub_key(), &sender_key, &wtx.pub_key(), 20);
+
+    let sender_wallet = get_wallet(&testkit, tx_sender.pub_key());
+    assert_eq!(sender_wallet.available(&native_currency()), BALANCE - 90);
+    assert_eq!(sender_wallet.frozen(&native_currency()), 90);
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_transfer_to_nonexistent_wallet() {
+    let mut testkit = init_testkit();
+    let (tx_sender, sender_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let (receiver, _) = crypto::gen_keypair();
+
+    let _ttx = transfer(&mut testkit, &tx_sender.pub_key(), &sender_key, &receiver, 30);
+
+    assert_eq!(get_wallet(&testkit, tx_sender.pub_key()).available(&native_currency()), BALANCE);
+    assert!(Schema::new(&testkit.snapshot()).wallet(&receiver).is_none());
+}
+
+#[test]
+fn test_tx_transfer_of_zero_amount() {
+    let mut testkit = init_testkit();
+    let (tx_sender, sender_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let (tx_receiver, _) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let currency = register_currency(&mut testkit).hash();
+
+    let seed = testkit.height().0;
+    let ttx = Transfer::new(tx_sender.pub_key(), tx_receiver.pub_key(), &currency, 0, seed, &sender_key);
+    create_block_with_transaction(&mut testkit, ttx.clone());
+
+    assert_eq!(tx_error_code(&testkit, &ttx.hash()), Some(Error::ZeroTransferAmount as u8));
+    assert!(get_wallet(&testkit, tx_sender.pub_key()).balances().iter().all(|b| b.currency() != &currency));
+    assert!(get_wallet(&testkit, tx_receiver.pub_key()).balances().iter().all(|b| b.currency() != &currency));
+}
+
+#[test]
+fn test_tx_transfer_of_unknown_currency() {
+    let mut testkit = init_testkit();
+    let (tx_sender, sender_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let (tx_receiver, _) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let currency = hash(b"unknown");
+
+    let seed = testkit.height().0;
+    let ttx = Transfer::new(tx_sender.pub_key(), tx_receiver.pub_key(), &currency, 10, seed, &sender_key);
+    create_block_with_transaction(&mut testkit, ttx.clone());
+
+    assert_eq!(tx_error_code(&testkit, &ttx.hash()), Some(Error::CurrencyNotFound as u8));
+    assert!(get_wallet(&testkit, tx_sender.pub_key()).balances().iter().all(|b| b.currency() != &currency));
+    assert!(get_wallet(&testkit, tx_receiver.pub_key()).balances().iter().all(|b| b.currency() != &currency));
+}
+
+#[test]
+fn test_tx_register_currency() {
+    let mut testkit = init_testkit();
+    let (tx, _) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+
+    let ctx = register_currency(&mut testkit);
+    let currency = Schema::new(&testkit.snapshot()).currency(&ctx.hash()).expect("No currency persisted");
+    assert_eq!(currency.name(), "gold");
+    assert_eq!(currency.decimals(), 2);
+    assert_eq!(currency.issuer(), &issuer_keys().0);
+
+    issue(&mut testkit, tx.pub_key(), &ctx.hash(), 40);
+
+    let wallet = get_wallet(&testkit, tx.pub_key());
+    assert_eq!(wallet.available(&ctx.hash()), 40);
+    assert_eq!(wallet.available(&native_currency()), BALANCE);
+    assert_eq!(Schema::new(&testkit.snapshot()).total_issuance(&ctx.hash()), 40);
+}
+
+#[test]
+fn test_tx_register_currency_by_non_issuer() {
+    let mut testkit = init_testkit();
+    let (tx, key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+
+    let ctx = RegisterCurrency::new(&tx.pub_key(), "gold", 2, 0, &key);
+    create_block_with_transaction(&mut testkit, ctx.clone());
+
+    assert!(Schema::new(&testkit.snapshot()).currency(&ctx.hash()).is_none());
+}
+
+#[test]
+fn test_tx_issue_unknown_currency() {
+    let mut testkit = init_testkit();
+    let (tx, _) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let currency = hash(b"unknown");
+
+    issue(&mut testkit, tx.pub_key(), &currency, 40);
+
+    assert_eq!(get_wallet(&testkit, tx.pub_key()).available(&currency), 0);
+    assert_eq!(Schema::new(&testkit.snapshot()).total_issuance(&currency), 0);
+}
+
+#[test]
+fn test_tx_bid_on_lot_in_currency() {
+    let mut testkit = init_testkit();
+    let currency = register_currency(&mut testkit).hash();
+    let (ltx, tx_owner, owner_key) = create_lot(&mut testkit, LotBuilder::new().currency(&currency));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    // The bidder holds no funds in the lot's currency yet
+    place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 25);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
+
+    issue(&mut testkit, tx_bidder.pub_key(), &currency, 50);
+    place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 20);
+
+    let bidder = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder.available(&currency), 30);
+    assert_eq!(bidder.frozen(&currency), 20);
+    assert_eq!(bidder.available(&native_currency()), BALANCE);
+
+    close_lot(&mut testkit, &tx_owner.pub_key(), &owner_key, &ltx.hash());
+
+    let bidder = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder.frozen(&currency), 0);
+    assert_eq!(bidder.available(&native_currency()), BALANCE);
+    let owner = get_wallet(&testkit, tx_owner.pub_key());
+    assert_eq!(owner.available(&currency), 20);
+    assert_eq!(owner.available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_create_lot_in_unknown_currency() {
+    let mut testkit = init_testkit();
+    let currency = hash(b"unknown");
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().currency(&currency));
+
+    assert!(Schema::new(&testkit.snapshot()).lot(&ltx.hash()).is_none());
+}
+
+#[test]
+fn test_tx_lot_with_asset() {
+    let mut testkit = init_testkit();
+    let (tx_owner, owner_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let atx = issue_asset(&mut testkit, &tx_owner.pub_key(), &owner_key);
+    assert_eq!(get_asset(&testkit, &atx.hash()).owner(), tx_owner.pub_key());
+
+    let ltx = create_lot_by(&mut testkit, &tx_owner.pub_key(), &owner_key, LotBuilder::new().asset(&atx.hash()));
+    assert_eq!(get_asset(&testkit, &atx.hash()).lot(), &ltx.hash());
+
+    // The asset can't be sold in two lots at once
+    let second_ltx = create_lot_by(&mut testkit, &tx_owner.pub_key(), &owner_key, LotBuilder::new().name("second").asset(&atx.hash()));
+    assert!(Schema::new(&testkit.snapshot()).lot(&second_ltx.hash()).is_none());
+
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 20);
+    close_lot(&mut testkit, &tx_owner.pub_key(), &owner_key, &ltx.hash());
+
+    let asset = get_asset(&testkit, &atx.hash());
+    assert_eq!(asset.owner(), tx_bidder.pub_key());
+    assert!(!asset.is_locked());
+    assert_eq!(get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()), BALANCE + 20);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE - 20);
+}
+
+#[test]
+fn test_tx_lot_with_asset_cancelled() {
+    let mut testkit = init_testkit();
+    let (tx_owner, owner_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let atx = issue_asset(&mut testkit, &tx_owner.pub_key(), &owner_key);
+    let ltx = create_lot_by(&mut testkit, &tx_owner.pub_key(), &owner_key, LotBuilder::new().asset(&atx.hash()));
+
+    create_block_with_transaction(&mut testkit, CancelLot::new(&tx_owner.pub_key(), &ltx.hash(), &owner_key));
+
+    let asset = get_asset(&testkit, &atx.hash());
+    assert_eq!(asset.owner(), tx_owner.pub_key());
+    assert!(!asset.is_locked());
+
+    // The unlocked asset can be sold again
+    let second_ltx = create_lot_by(&mut testkit, &tx_owner.pub_key(), &owner_key, LotBuilder::new().name("second").asset(&atx.hash()));
+    assert_eq!(get_asset(&testkit, &atx.hash()).lot(), &second_ltx.hash());
+}
+
+#[test]
+fn test_tx_lot_with_foreign_asset() {
+    let mut testkit = init_testkit();
+    let (tx_owner, owner_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let atx = issue_asset(&mut testkit, &tx_owner.pub_key(), &owner_key);
+    let (tx_seller, seller_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let ltx = create_lot_by(&mut testkit, &tx_seller.pub_key(), &seller_key, LotBuilder::new().asset(&atx.hash()));
+
+    assert!(Schema::new(&testkit.snapshot()).lot(&ltx.hash()).is_none());
+    assert!(!get_asset(&testkit, &atx.hash()).is_locked());
+}
+
+#[test]
+fn test_tx_escrow_confirm_delivery() {
+    let mut testkit = init_testkit_with_config(AuctionConfig { escrow_period: ESCROW_PERIOD, ..config() });
+    let (ltx, tx_owner, owner_key) = create_lot(&mut testkit, LotBuilder::new());
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 20);
+    close_lot(&mut testkit, &tx_owner.pub_key(), &owner_key, &ltx.hash());
+
+    // The payment stays frozen until the delivery is confirmed
+    assert!(get_lot(&testkit, &ltx.hash()).is_in_escrow());
+    let escrow = Schema::new(&testkit.snapshot()).escrow(&ltx.hash()).expect("No escrow persisted");
+    assert_eq!(escrow.buyer(), tx_bidder.pub_key());
+    assert_eq!(escrow.amount(), 20);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).frozen(&native_currency()), 20);
+    assert_eq!(get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()), BALANCE);
+
+    // Only the buyer can confirm the delivery
+    create_block_with_transaction(&mut testkit, ConfirmDelivery::new(&tx_owner.pub_key(), &ltx.hash(), &owner_key));
+    assert!(get_lot(&testkit, &ltx.hash()).is_in_escrow());
+
+    create_block_with_transaction(&mut testkit, ConfirmDelivery::new(&tx_bidder.pub_key(), &ltx.hash(), &bidder_key));
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert!(Schema::new(&testkit.snapshot()).escrow(&ltx.hash()).is_none());
+    let bidder = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder.available(&native_currency()), BALANCE - 20);
+    assert_eq!(bidder.frozen(&native_currency()), 0);
+    assert_eq!(get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()), BALANCE + 20);
+}
+
+#[test]
+fn test_tx_escrow_timeout() {
+    let mut testkit = init_testkit_with_config(AuctionConfig { escrow_period: ESCROW_PERIOD, ..config() });
+    let (tx_owner, owner_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let atx = issue_asset(&mut testkit, &tx_owner.pub_key(), &owner_key);
+    let ltx = create_lot_by(&mut testkit, &tx_owner.pub_key(), &owner_key, LotBuilder::new().asset(&atx.hash()));
+    let (tx_buyer, buyer_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    place_bid(&mut testkit, &tx_buyer.pub_key(), &buyer_key, &ltx.hash(), 20);
+    close_lot(&mut testkit, &tx_owner.pub_key(), &owner_key, &ltx.hash());
+    let release_height = Schema::new(&testkit.snapshot()).escrow(&ltx.hash()).expect("No escrow persisted").release_height();
+    assert_eq!(release_height, testkit.height().0 + ESCROW_PERIOD);
+
+    create_blocks_until(&mut testkit, Height(release_height - 1));
+    assert!(get_lot(&testkit, &ltx.hash()).is_in_escrow());
+    assert_eq!(get_asset(&testkit, &atx.hash()).owner(), tx_owner.pub_key());
+
+    // The payment and the asset change hands together once the escrow period passes
+    create_blocks_until(&mut testkit, Height(release_height));
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_asset(&testkit, &atx.hash()).owner(), tx_buyer.pub_key());
+    assert_eq!(get_wallet(&testkit, tx_buyer.pub_key()).frozen(&native_currency()), 0);
+    assert_eq!(get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()), BALANCE + 20);
+}
+
+#[test]
+fn test_tx_dispute_resolved_with_split() {
+    let mut testkit = init_testkit_with_config(AuctionConfig { escrow_period: ESCROW_PERIOD, ..config() });
+    let (tx_owner, owner_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let atx = issue_asset(&mut testkit, &tx_owner.pub_key(), &owner_key);
+    let ltx = create_lot_by(&mut testkit, &tx_owner.pub_key(), &owner_key, LotBuilder::new().asset(&atx.hash()));
+    let (tx_buyer, buyer_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    place_bid(&mut testkit, &tx_buyer.pub_key(), &buyer_key, &ltx.hash(), 20);
+    close_lot(&mut testkit, &tx_owner.pub_key(), &owner_key, &ltx.hash());
+    let release_height = Schema::new(&testkit.snapshot()).escrow(&ltx.hash()).expect("No escrow persisted").release_height();
+
+    // Only the buyer can open a dispute
+    create_block_with_transaction(&mut testkit, OpenDispute::new(&tx_owner.pub_key(), &ltx.hash(), "no", &owner_key));
+    assert!(get_lot(&testkit, &ltx.hash()).is_in_escrow());
+
+    let otx = OpenDispute::new(&tx_buyer.pub_key(), &ltx.hash(), "damaged", &buyer_key);
+    create_block_with_transaction(&mut testkit, otx.clone());
+    assert!(get_lot(&testkit, &ltx.hash()).is_disputed());
+
+    // The payment is no longer released when the escrow period passes
+    create_blocks_until(&mut testkit, Height(release_height + 1));
+    assert!(get_lot(&testkit, &ltx.hash()).is_disputed());
+    assert_eq!(get_wallet(&testkit, tx_buyer.pub_key()).frozen(&native_currency()), 20);
+
+    let (arbiter, arbiter_key) = arbiter_keys();
+    let rtx = ResolveDispute::new(&arbiter, &ltx.hash(), 5, 15, "partially damaged", &arbiter_key);
+    create_block_with_transaction(&mut testkit, rtx.clone());
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert!(Schema::new(&testkit.snapshot()).escrow(&ltx.hash()).is_none());
+    assert_eq!(get_asset(&testkit, &atx.hash()).owner(), tx_buyer.pub_key());
+    let buyer = get_wallet(&testkit, tx_buyer.pub_key());
+    assert_eq!(buyer.available(&native_currency()), BALANCE - 15);
+    assert_eq!(buyer.frozen(&native_currency()), 0);
+    assert_eq!(get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()), BALANCE + 15);
+
+    let snapshot = testkit.snapshot();
+    let schema = Schema::new(&snapshot);
+    let history = schema.dispute_history(&ltx.hash());
+    assert_eq!(history.len(), 2);
+    assert_eq!(history.get(0).unwrap().tx_hash(), &otx.hash());
+    assert_eq!(history.get(1).unwrap().tx_hash(), &rtx.hash());
+    let dispute = schema.disputes().get(&ltx.hash()).expect("No dispute persisted");
+    assert_eq!(dispute.history_len(), 2);
+    assert_eq!(dispute.history_hash(), &history.merkle_root());
+}
+
+#[test]
+fn test_tx_resolve_dispute_by_non_arbiter() {
+    let mut testkit = init_testkit_with_config(AuctionConfig { escrow_period: ESCROW_PERIOD, ..config() });
+    let (ltx, tx_owner, owner_key) = create_lot(&mut testkit, LotBuilder::new());
+    let (tx_buyer, buyer_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    place_bid(&mut testkit, &tx_buyer.pub_key(), &buyer_key, &ltx.hash(), 20);
+    close_lot(&mut testkit, &tx_owner.pub_key(), &owner_key, &ltx.hash());
+    create_block_with_transaction(&mut testkit, OpenDispute::new(&tx_buyer.pub_key(), &ltx.hash(), "damaged", &buyer_key));
+
+    create_block_with_transaction(&mut testkit, ResolveDispute::new(&tx_owner.pub_key(), &ltx.hash(), 0, 20, "", &owner_key));
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_disputed());
+    assert_eq!(get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_resolve_dispute_with_invalid_split() {
+    let mut testkit = init_testkit_with_config(AuctionConfig { escrow_period: ESCROW_PERIOD, ..config() });
+    let (ltx, tx_owner, owner_key) = create_lot(&mut testkit, LotBuilder::new());
+    let (tx_buyer, buyer_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    place_bid(&mut testkit, &tx_buyer.pub_key(), &buyer_key, &ltx.hash(), 20);
+    close_lot(&mut testkit, &tx_owner.pub_key(), &owner_key, &ltx.hash());
+    create_block_with_transaction(&mut testkit, OpenDispute::new(&tx_buyer.pub_key(), &ltx.hash(), "damaged", &buyer_key));
+
+    let (arbiter, arbiter_key) = arbiter_keys();
+    create_block_with_transaction(&mut testkit, ResolveDispute::new(&arbiter, &ltx.hash(), 10, 15, "", &arbiter_key));
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_disputed());
+    assert_eq!(get_wallet(&testkit, tx_buyer.pub_key()).frozen(&native_currency()), 20);
+    assert_eq!(Schema::new(&testkit.snapshot()).dispute_history(&ltx.hash()).len(), 1);
+}
+
+#[test]
+fn test_tx_listing_fee_and_commission() {
+    let mut testkit = init_testkit_with_fees();
+    let (tx_owner, owner_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let ltx = create_lot_by(&mut testkit, &tx_owner.pub_key(), &owner_key, LotBuilder::new());
+    assert_eq!(get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()), BALANCE - LISTING_FEE);
+
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 20);
+    close_lot(&mut testkit, &tx_owner.pub_key(), &owner_key, &ltx.hash());
+    assert_supply_invariant(&testkit);
+
+    // The seller receives the price less the commission
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE - 20);
+    assert_eq!(get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()), BALANCE - LISTING_FEE + 18);
+    assert_eq!(get_wallet(&testkit, &treasury_keys().0).available(&native_currency()), LISTING_FEE + 2);
+    assert_eq!(Schema::new(&testkit.snapshot()).treasury().get(&native_currency()), Some(LISTING_FEE + 2));
+}
+
+#[test]
+fn test_tx_commission_on_large_price() {
+    let mut testkit = init_testkit_with_fees();
+    let (tx_owner, owner_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let ltx = create_lot_by(&mut testkit, &tx_owner.pub_key(), &owner_key, LotBuilder::new());
+
+    // The price times the percentage doesn't fit into u64, the commission is still rounded down only once
+    let price = 9_223_372_036_854_775_899;
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, price);
+    place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), price);
+    close_lot(&mut testkit, &tx_owner.pub_key(), &owner_key, &ltx.hash());
+    assert_supply_invariant(&testkit);
+
+    let commission = 922_337_203_685_477_589;
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), 0);
+    assert_eq!(
+        get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()),
+        BALANCE - LISTING_FEE + price - commission
+    );
+    assert_eq!(get_wallet(&testkit, &treasury_keys().0).available(&native_currency()), LISTING_FEE + commission);
+}
+
+#[test]
+fn test_tx_config_with_excessive_commission() {
+    let value = serde_json::json!({ "commission_percent": MAX_COMMISSION_PERCENT + 1 });
+    assert!(serde_json::from_value::<AuctionConfig>(value).is_err());
+
+    let value = serde_json::json!({ "commission_percent": MAX_COMMISSION_PERCENT });
+    let config = serde_json::from_value::<AuctionConfig>(value).unwrap();
+    assert_eq!(config.commission_percent, MAX_COMMISSION_PERCENT);
+}
+
+#[test]
+fn test_tx_config_with_excessive_escrow_period() {
+    let value = serde_json::json!({ "escrow_period": MAX_ESCROW_PERIOD + 1 });
+    assert!(serde_json::from_value::<AuctionConfig>(value).is_err());
+
+    let value = serde_json::json!({ "escrow_period": MAX_ESCROW_PERIOD });
+    let config = serde_json::from_value::<AuctionConfig>(value).unwrap();
+    assert_eq!(config.escrow_period, MAX_ESCROW_PERIOD);
+}
+
+#[test]
+#[should_panic(expected = "escrow_period can't exceed")]
+fn test_tx_service_with_excessive_escrow_period() {
+    Service::new(AuctionConfig { escrow_period: MAX_ESCROW_PERIOD + 1, ..config() });
+}
+
+#[test]
+fn test_tx_config_change_with_excessive_commission() {
+    let mut testkit = init_testkit_with_fees();
+    change_config(&mut testkit, AuctionConfig { listing_fee: LISTING_FEE + 1, ..fees_config() });
+
+    // The invalid configuration is ignored, the previous one stays in effect as a whole
+    change_config(&mut testkit, AuctionConfig { commission_percent: MAX_COMMISSION_PERCENT + 1, ..fees_config() });
+    let config = Schema::new(&testkit.snapshot()).config();
+    assert_eq!(config.issuers, vec![issuer_keys().0]);
+    assert_eq!(config.treasury, Some(treasury_keys().0));
+    assert_eq!(config.listing_fee, LISTING_FEE + 1);
+    assert_eq!(config.commission_percent, COMMISSION_PERCENT);
+
+    let (tx_owner, _) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    assert_eq!(get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+#[should_panic(expected = "commission_percent can't exceed")]
+fn test_tx_service_with_excessive_commission() {
+    Service::new(AuctionConfig { commission_percent: MAX_COMMISSION_PERCENT + 1, ..config() });
+}
+
+#[test]
+fn test_tx_listing_fee_unpaid() {
+    let mut testkit = init_testkit_with_fees();
+    let (tx_owner, owner_key) = create_wallet(&mut testkit, PHOBOS, LISTING_FEE - 1);
+
+    let ltx = create_lot_by(&mut testkit, &tx_owner.pub_key(), &owner_key, LotBuilder::new());
+
+    assert!(Schema::new(&testkit.snapshot()).lot(&ltx.hash()).is_none());
+    assert_eq!(get_wallet(&testkit, tx_owner.pub_key()).available(&native_currency()), LISTING_FEE - 1);
+    assert!(Schema::new(&testkit.snapshot()).treasury().get(&native_currency()).is_none());
 }
 
 #[test]
 fn test_tx_create_lot() {
     let mut testkit = init_testkit();
     let lot_name = format!("{}'s test lot", PHOBOS);
-    let (ltx, wtx, _) = create_lot(&mut testkit, lot_name.as_str(), MIN_BID);
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().name(lot_name.as_str()));
 
     // Check that the user indeed is persisted by the service
     let lot = get_lot(&testkit, &ltx.hash());
@@ -46,7 +533,7 @@ fn test_tx_create_lot() {
 #[test]
 fn test_tx_place_bids() {
     let mut testkit = init_testkit();
-    let (ltx, _, _) = create_lot(&mut testkit, format!("{}'s test lot", PHOBOS).as_str(), MIN_BID);
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
 
     let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
 
@@ -59,27 +546,76 @@ fn test_tx_place_bids() {
         assert_eq!(bid.amount(), amount);
 
         let bidder_wallet = get_wallet(&testkit, tx_bidder.pub_key());
-        assert_eq!(bidder_wallet.balance(), BALANCE - amount);
+        assert_eq!(bidder_wallet.available(&native_currency()), BALANCE - amount);
     }
 }
 
+#[test]
+fn test_tx_bids_on_several_lots() {
+    let mut testkit = init_testkit();
+    let (first_ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s first lot", PHOBOS)));
+    let (second_ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s second lot", PHOBOS)));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &first_ltx.hash(), 60);
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &second_ltx.hash(), 30);
+    assert_eq!(bid_history_size(&testkit, &second_ltx.hash()), 1);
+
+    let bidder_wallet = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder_wallet.available(&native_currency()), BALANCE - 90);
+    assert_eq!(bidder_wallet.frozen(&native_currency()), 90);
+
+    // Only the remaining available funds can be frozen
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &second_ltx.hash(), 41);
+    assert_eq!(bid_history_size(&testkit, &second_ltx.hash()), 1);
+}
+
+#[test]
+fn test_tx_place_bid_with_absolute_increment() {
+    let mut testkit = init_testkit();
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().increment(5, 0));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID);
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID + 4);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 1);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID + 5);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 2);
+    assert_eq!(last_bid(&testkit, &ltx.hash()).amount(), MIN_BID + 5);
+}
+
+#[test]
+fn test_tx_place_bid_with_percentage_increment() {
+    let mut testkit = init_testkit();
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().increment(0, 50));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), 20);
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), 29);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 1);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), 30);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 2);
+}
+
 #[test]
 fn test_tx_create_existing_wallet() {
     let mut testkit = init_testkit();
     let (tx, key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
-    testkit.create_block_with_transaction(CreateWallet::new(&tx.pub_key(), format!("{}'s test lot", PHOBOS).as_str(), BALANCE + 20, &key));
+    create_block_with_transaction(&mut testkit, CreateWallet::new(&tx.pub_key(), format!("{}'s test lot", PHOBOS).as_str(), &key));
 
     // Check that the user indeed is persisted by the service
     let wallet = get_wallet(&testkit, tx.pub_key());
     assert_eq!(wallet.name(), PHOBOS);
-    assert_eq!(wallet.balance(), BALANCE);
+    assert_eq!(wallet.available(&native_currency()), BALANCE);
 }
 
 #[test]
 fn test_tx_create_lot_for_nonexistent_wallet() {
     let mut testkit = init_testkit();
     let (pubkey, key) = crypto::gen_keypair();
-    testkit.create_block_with_transaction(CreateLot::new(&pubkey, "test", 0, &key));
+    create_block_with_transaction(&mut testkit, LotBuilder::new().min_bid(0).sign(&pubkey, &key));
 
     assert_eq!(lots_total(&testkit), 0);
 }
@@ -90,7 +626,7 @@ fn test_tx_place_bid_on_nonexistent_lot() {
     let (tx, key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
     let data = [1, 2, 3];
     let hash = hash(&data);
-    testkit.create_block_with_transaction(PlaceBid::new(&tx.pub_key(), &hash, 10, &key));
+    create_block_with_transaction(&mut testkit, PlaceBid::new(&tx.pub_key(), &hash, 10, 1, &key));
 
     assert_eq!(bid_history_size(&testkit, &hash), 0);
 }
@@ -98,7 +634,7 @@ fn test_tx_place_bid_on_nonexistent_lot() {
 #[test]
 fn test_tx_place_bid_on_own_lot() {
     let mut testkit = init_testkit();
-    let (ltx, wtx, key) = create_lot(&mut testkit, format!("{}'s test lot", PHOBOS).as_str(), MIN_BID);
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
 
     let _btx = place_bid(&mut testkit, &wtx.pub_key(), &key, &ltx.hash(), MIN_BID);
     assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
@@ -107,7 +643,7 @@ fn test_tx_place_bid_on_own_lot() {
 #[test]
 fn test_tx_place_bid_below_minimum() {
     let mut testkit = init_testkit();
-    let (ltx, _wtx, _) = create_lot(&mut testkit, format!("{}'s test lot", PHOBOS).as_str(), MIN_BID);
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
     let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
 
     let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID - 1);
@@ -117,7 +653,7 @@ fn test_tx_place_bid_below_minimum() {
 #[test]
 fn test_tx_place_bid_below_current_highest_bid() {
     let mut testkit = init_testkit();
-    let (ltx, _wtx, _) = create_lot(&mut testkit, format!("{}'s test lot", PHOBOS).as_str(), MIN_BID);
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
     let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
 
     for n in 1..=2 {
@@ -131,56 +667,1508 @@ fn test_tx_place_bid_below_current_highest_bid() {
 #[test]
 fn test_tx_place_bid_above_balance() {
     let mut testkit = init_testkit();
-    let (ltx, _, _) = create_lot(&mut testkit, format!("{}'s test lot", PHOBOS).as_str(), MIN_BID);
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
     let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
 
     let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), BALANCE + 1);
     assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
 
     let bidder_wallet = get_wallet(&testkit, tx_bidder.pub_key());
-    assert_eq!(bidder_wallet.balance(), BALANCE);
+    assert_eq!(bidder_wallet.available(&native_currency()), BALANCE);
 }
 
-/// Initializes testkit with `Service`.
-fn init_testkit() -> TestKit {
-    TestKitBuilder::validator()
-        .with_service(auction::Service)
-        .create()
+#[test]
+fn test_tx_close_lot() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), MIN_BID);
+    close_lot(&mut testkit, &wtx.pub_key(), &key, &ltx.hash());
+
+    let lot = get_lot(&testkit, &ltx.hash());
+    assert!(lot.is_closed());
+
+    let owner_wallet = get_wallet(&testkit, wtx.pub_key());
+    assert_eq!(owner_wallet.available(&native_currency()), BALANCE + MIN_BID);
+
+    let bidder_wallet = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder_wallet.available(&native_currency()), BALANCE - MIN_BID);
+    assert_eq!(bidder_wallet.frozen(&native_currency()), 0);
 }
 
-/// Creates a wallet with the given name and a random key.
-fn create_wallet(testkit: &mut TestKit, name: &str, balance: u64) -> (CreateWallet, SecretKey) {
-    let (pubkey, key) = crypto::gen_keypair();
-    let tx = CreateWallet::new(&pubkey, name, balance, &key);
-    testkit.create_block_with_transaction(tx.clone());
-    (tx, key)
+#[test]
+fn test_tx_close_lot_without_bids() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
+
+    close_lot(&mut testkit, &wtx.pub_key(), &key, &ltx.hash());
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE);
 }
 
-/// Returns the wallet identified by the given public key.
-fn get_wallet(testkit: &TestKit, pubkey: &PublicKey) -> Wallet {
-    Schema::new(&testkit.snapshot()).wallet(pubkey).expect("No wallet persisted")
+#[test]
+fn test_tx_close_lot_by_non_owner() {
+    let mut testkit = init_testkit();
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    close_lot(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash());
+    assert!(!get_lot(&testkit, &ltx.hash()).is_closed());
 }
 
-fn create_lot(testkit: &mut TestKit, name: &str, min_bid: u64) -> (CreateLot, CreateWallet, SecretKey) {
-    let (tx, key) = create_wallet(testkit, PHOBOS, BALANCE);
+#[test]
+fn test_tx_place_bid_on_closed_lot() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
 
-    let ltx = CreateLot::new(&tx.pub_key(), name, min_bid, &key);
-    testkit.create_block_with_transaction(ltx.clone());
-    (ltx, tx, key)
+    close_lot(&mut testkit, &wtx.pub_key(), &key, &ltx.hash());
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), MIN_BID);
+
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE);
 }
 
-fn get_lot(testkit: &TestKit, id: &Hash) -> Lot {
-    Schema::new(&testkit.snapshot()).lot(id).expect("No lot persisted")
+#[test]
+fn test_tx_cancel_lot() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), MIN_BID);
+    create_block_with_transaction(&mut testkit, CancelLot::new(&wtx.pub_key(), &ltx.hash(), &key));
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_cancelled());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE);
+
+    let bidder_wallet = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder_wallet.available(&native_currency()), BALANCE);
+    assert_eq!(bidder_wallet.frozen(&native_currency()), 0);
+
+    // Bids on cancelled lots are rejected
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), MIN_BID + 1);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 1);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE);
 }
 
-fn place_bid(testkit: &mut TestKit, bidder: &PublicKey, key: &SecretKey, lot_id: &Hash, bid: u64) -> PlaceBid {
-    let tx = PlaceBid::new(&bidder, lot_id, bid, key);
-    testkit.create_block_with_transaction(tx.clone());
-    tx
+#[test]
+fn test_tx_cancel_lot_by_non_owner() {
+    let mut testkit = init_testkit();
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    create_block_with_transaction(&mut testkit, CancelLot::new(&tx_bidder.pub_key(), &ltx.hash(), &key));
+    assert!(get_lot(&testkit, &ltx.hash()).is_open());
 }
 
-fn last_bid(testkit: &TestKit, lot_id: &Hash) -> Bid {
-    Schema::new(&testkit.snapshot()).last_bid(lot_id).expect("No bid history for lot")
+#[test]
+fn test_tx_cancel_lot_after_deadline() {
+    let mut testkit = init_testkit();
+    let end_height = testkit.height().0 + 3;
+    let salt = hash(&[4, 2]);
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().end_height(end_height).reserve(50, &salt));
+
+    create_blocks_until(&mut testkit, Height(end_height));
+    create_block_with_transaction(&mut testkit, CancelLot::new(&wtx.pub_key(), &ltx.hash(), &key));
+    assert!(get_lot(&testkit, &ltx.hash()).is_open());
+}
+
+#[test]
+fn test_tx_lot_closed_at_deadline() {
+    let mut testkit = init_testkit();
+    let end_height = testkit.height().0 + 5;
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)).end_height(end_height));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID);
+    create_blocks_until(&mut testkit, Height(end_height - 1));
+    assert!(!get_lot(&testkit, &ltx.hash()).is_closed());
+
+    create_block(&mut testkit);
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + MIN_BID);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_place_bid_after_deadline() {
+    let mut testkit = init_testkit();
+    let end_height = testkit.height().0 + 3;
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)).end_height(end_height));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    create_blocks_until(&mut testkit, Height(end_height));
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID);
+
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_create_lot_with_past_deadline() {
+    let mut testkit = init_testkit();
+    let end_height = testkit.height().0 + 1;
+    let _ = create_lot(&mut testkit, LotBuilder::new().end_height(end_height));
+
+    assert_eq!(lots_total(&testkit), 0);
+}
+
+#[test]
+fn test_tx_close_lot_before_deadline() {
+    let mut testkit = init_testkit();
+    let end_height = testkit.height().0 + 10;
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().name(&format!("{}'s test lot", PHOBOS)).end_height(end_height));
+
+    close_lot(&mut testkit, &wtx.pub_key(), &key, &ltx.hash());
+    assert!(!get_lot(&testkit, &ltx.hash()).is_closed());
+}
+
+#[test]
+fn test_tx_bid_near_deadline_extends_it() {
+    let mut testkit = init_testkit();
+    let end_height = testkit.height().0 + 5;
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().end_height(end_height).extension(2, 3));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    // The bid lands one block before the deadline
+    create_blocks_until(&mut testkit, Height(end_height - 2));
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID);
+
+    let lot = get_lot(&testkit, &ltx.hash());
+    assert_eq!(lot.end_height(), end_height + 3);
+
+    let extensions = extensions(&testkit, &ltx.hash());
+    assert_eq!(extensions.len(), 1);
+    assert_eq!(extensions[0].bidder(), tx_bidder.pub_key());
+    assert_eq!(extensions[0].end_height(), end_height + 3);
+
+    create_blocks_until(&mut testkit, Height(end_height));
+    assert!(!get_lot(&testkit, &ltx.hash()).is_closed());
+
+    create_blocks_until(&mut testkit, Height(end_height + 3));
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + MIN_BID);
+}
+
+#[test]
+fn test_tx_early_bid_does_not_extend_deadline() {
+    let mut testkit = init_testkit();
+    let end_height = testkit.height().0 + 10;
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().end_height(end_height).extension(2, 3));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID);
+
+    assert_eq!(get_lot(&testkit, &ltx.hash()).end_height(), end_height);
+    assert!(extensions(&testkit, &ltx.hash()).is_empty());
+}
+
+#[test]
+fn test_tx_create_lot_with_excessive_extension() {
+    let mut testkit = init_testkit();
+    let end_height = testkit.height().0 + 10;
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().end_height(end_height).extension(2, u64::max_value()));
+    assert_eq!(tx_error_code(&testkit, &ltx.hash()), Some(Error::ExtensionTooLong as u8));
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().closes_at("2100-01-01T00:00:00Z").extension(MAX_EXTENSION + 1, 3));
+    assert_eq!(tx_error_code(&testkit, &ltx.hash()), Some(Error::ExtensionTooLong as u8));
+
+    assert_eq!(lots_total(&testkit), 0);
+}
+
+#[test]
+fn test_tx_close_lot_meeting_reserve() {
+    let mut testkit = init_testkit();
+    let salt = hash(&[4, 2]);
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().reserve(50, &salt));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 50);
+    close_lot_with_reserve(&mut testkit, &wtx.pub_key(), &key, &ltx.hash(), 50, &salt);
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 50);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_close_lot_below_reserve() {
+    let mut testkit = init_testkit();
+    let salt = hash(&[4, 2]);
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().reserve(50, &salt));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 49);
+    close_lot_with_reserve(&mut testkit, &wtx.pub_key(), &key, &ltx.hash(), 50, &salt);
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE);
+
+    let bidder_wallet = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder_wallet.available(&native_currency()), BALANCE);
+    assert_eq!(bidder_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_close_lot_with_wrong_reserve() {
+    let mut testkit = init_testkit();
+    let salt = hash(&[4, 2]);
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().reserve(50, &salt));
+
+    close_lot_with_reserve(&mut testkit, &wtx.pub_key(), &key, &ltx.hash(), 40, &salt);
+    assert!(!get_lot(&testkit, &ltx.hash()).is_closed());
+}
+
+#[test]
+fn test_tx_lot_with_reserve_awaits_reveal_after_deadline() {
+    let mut testkit = init_testkit();
+    let salt = hash(&[4, 2]);
+    let end_height = testkit.height().0 + 5;
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().end_height(end_height).reserve(50, &salt));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 60);
+    create_blocks_until(&mut testkit, Height(end_height));
+    assert!(!get_lot(&testkit, &ltx.hash()).is_closed());
+
+    close_lot_with_reserve(&mut testkit, &wtx.pub_key(), &key, &ltx.hash(), 50, &salt);
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 60);
+}
+
+#[test]
+fn test_tx_lot_with_reserve_closed_without_reveal() {
+    let mut testkit = init_testkit();
+    let salt = hash(&[4, 2]);
+    let end_height = testkit.height().0 + 5;
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().end_height(end_height).reserve(50, &salt));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 60);
+    create_blocks_until(&mut testkit, Height(end_height + RESERVE_REVEAL_PERIOD - 1));
+    assert!(get_lot(&testkit, &ltx.hash()).is_open());
+
+    // The owner never reveals the reserve, so the lot is closed without a sale
+    create_block(&mut testkit);
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE);
+    let bidder = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder.available(&native_currency()), BALANCE);
+    assert_eq!(bidder.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_buy_now() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().buy_now(50));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_buyer, buyer_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), MIN_BID);
+    create_block_with_transaction(&mut testkit, BuyNow::new(&tx_buyer.pub_key(), &ltx.hash(), &buyer_key));
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(last_bid(&testkit, &ltx.hash()).owner(), tx_buyer.pub_key());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 50);
+
+    let buyer_wallet = get_wallet(&testkit, tx_buyer.pub_key());
+    assert_eq!(buyer_wallet.available(&native_currency()), BALANCE - 50);
+    assert_eq!(buyer_wallet.frozen(&native_currency()), 0);
+
+    let bidder_wallet = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder_wallet.available(&native_currency()), BALANCE);
+    assert_eq!(bidder_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_bid_above_buy_now_price() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().buy_now(50));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), 70);
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(last_bid(&testkit, &ltx.hash()).amount(), 50);
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 50);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE - 50);
+}
+
+#[test]
+fn test_tx_buy_now_unavailable() {
+    let mut testkit = init_testkit();
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new());
+    let (tx_buyer, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    create_block_with_transaction(&mut testkit, BuyNow::new(&tx_buyer.pub_key(), &ltx.hash(), &key));
+
+    assert!(!get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, tx_buyer.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_place_bid_before_time_deadline() {
+    let provider = MockTimeProvider::default();
+    let mut testkit = init_testkit_with_time(&provider);
+    let start = Utc.timestamp(1_500_000_000, 0);
+    set_consensus_time(&mut testkit, &provider, start);
+
+    let closes_at = start + Duration::seconds(60);
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().closes_at(&closes_at.to_rfc3339()));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    set_consensus_time(&mut testkit, &provider, closes_at - Duration::seconds(1));
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID);
+
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 1);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).frozen(&native_currency()), MIN_BID);
+}
+
+#[test]
+fn test_tx_place_bid_after_time_deadline() {
+    let provider = MockTimeProvider::default();
+    let mut testkit = init_testkit_with_time(&provider);
+    let start = Utc.timestamp(1_500_000_000, 0);
+    set_consensus_time(&mut testkit, &provider, start);
+
+    let closes_at = start + Duration::seconds(60);
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().closes_at(&closes_at.to_rfc3339()));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    set_consensus_time(&mut testkit, &provider, closes_at + Duration::seconds(1));
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID);
+
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_lot_closed_at_time_deadline() {
+    let provider = MockTimeProvider::default();
+    let mut testkit = init_testkit_with_time(&provider);
+    let start = Utc.timestamp(1_500_000_000, 0);
+    set_consensus_time(&mut testkit, &provider, start);
+
+    let closes_at = start + Duration::seconds(60);
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().closes_at(&closes_at.to_rfc3339()));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID);
+
+    set_consensus_time(&mut testkit, &provider, closes_at - Duration::seconds(1));
+    assert!(get_lot(&testkit, &ltx.hash()).is_open());
+
+    // The lot is closed by the first block after the consensus time reaches the deadline
+    set_consensus_time(&mut testkit, &provider, closes_at);
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + MIN_BID);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).frozen(&native_currency()), 0);
+    assert!(!Schema::new(&testkit.snapshot()).timed_lots().contains(&ltx.hash()));
+}
+
+#[test]
+fn test_tx_lot_with_reserve_closed_after_time_deadline() {
+    let provider = MockTimeProvider::default();
+    let mut testkit = init_testkit_with_time(&provider);
+    let start = Utc.timestamp(1_500_000_000, 0);
+    set_consensus_time(&mut testkit, &provider, start);
+
+    let salt = hash(&[4, 2]);
+    let closes_at = start + Duration::seconds(60);
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().closes_at(&closes_at.to_rfc3339()).reserve(50, &salt));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), 60);
+
+    // The owner has `RESERVE_REVEAL_PERIOD` blocks to reveal the reserve after the deadline
+    set_consensus_time(&mut testkit, &provider, closes_at);
+    assert!(get_lot(&testkit, &ltx.hash()).is_open());
+
+    let timeout = testkit.height().0 + RESERVE_REVEAL_PERIOD;
+    create_blocks_until(&mut testkit, Height(timeout));
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE);
+    let bidder = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder.available(&native_currency()), BALANCE);
+    assert_eq!(bidder.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_close_lot_before_time_deadline() {
+    let provider = MockTimeProvider::default();
+    let mut testkit = init_testkit_with_time(&provider);
+    let start = Utc.timestamp(1_500_000_000, 0);
+    set_consensus_time(&mut testkit, &provider, start);
+
+    let closes_at = start + Duration::seconds(60);
+    let (ltx, wtx, key) = create_lot(&mut testkit, LotBuilder::new().closes_at(&closes_at.to_rfc3339()));
+
+    close_lot(&mut testkit, &wtx.pub_key(), &key, &ltx.hash());
+    assert!(!get_lot(&testkit, &ltx.hash()).is_closed());
+}
+
+#[test]
+fn test_tx_create_lot_with_invalid_closing_time() {
+    let mut testkit = init_testkit();
+    let _ = create_lot(&mut testkit, LotBuilder::new().closes_at("next tuesday"));
+
+    assert_eq!(lots_total(&testkit), 0);
+}
+
+#[test]
+fn test_tx_proxy_bid_outbids_competing_bid() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, owner_key) = create_lot(&mut testkit, LotBuilder::new());
+    let (tx_proxy, proxy_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    place_proxy_bid(&mut testkit, &tx_proxy.pub_key(), &proxy_key, &ltx.hash(), 50);
+    assert_eq!(last_bid(&testkit, &ltx.hash()).amount(), MIN_BID);
+    assert_eq!(get_wallet(&testkit, tx_proxy.pub_key()).frozen(&native_currency()), 50);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 30);
+
+    let bid = last_bid(&testkit, &ltx.hash());
+    assert_eq!(bid.owner(), tx_proxy.pub_key());
+    assert_eq!(bid.amount(), 31);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 3);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE);
+
+    // Only the final price is paid, the rest of the maximum is released
+    close_lot(&mut testkit, &wtx.pub_key(), &owner_key, &ltx.hash());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 31);
+
+    let proxy_wallet = get_wallet(&testkit, tx_proxy.pub_key());
+    assert_eq!(proxy_wallet.available(&native_currency()), BALANCE - 31);
+    assert_eq!(proxy_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_proxy_bid_outbid_by_higher_maximum() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, owner_key) = create_lot(&mut testkit, LotBuilder::new());
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    place_proxy_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 40);
+    place_proxy_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 60);
+
+    let bid = last_bid(&testkit, &ltx.hash());
+    assert_eq!(bid.owner(), tx_second.pub_key());
+    assert_eq!(bid.amount(), 41);
+
+    let first_wallet = get_wallet(&testkit, tx_first.pub_key());
+    assert_eq!(first_wallet.available(&native_currency()), BALANCE);
+    assert_eq!(first_wallet.frozen(&native_currency()), 0);
+    assert_eq!(get_wallet(&testkit, tx_second.pub_key()).frozen(&native_currency()), 60);
+
+    close_lot(&mut testkit, &wtx.pub_key(), &owner_key, &ltx.hash());
+    assert_eq!(get_wallet(&testkit, tx_second.pub_key()).available(&native_currency()), BALANCE - 41);
+}
+
+#[test]
+fn test_tx_proxy_bid_tie_won_by_first() {
+    let mut testkit = init_testkit();
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new());
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    place_proxy_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 50);
+    place_proxy_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 50);
+
+    let bid = last_bid(&testkit, &ltx.hash());
+    assert_eq!(bid.owner(), tx_first.pub_key());
+    assert_eq!(bid.amount(), 50);
+    assert_eq!(get_wallet(&testkit, tx_second.pub_key()).frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_proxy_bid_below_increment_over_competing_maximum() {
+    let mut testkit = init_testkit();
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().increment(5, 0));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    place_proxy_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 40);
+    // Beating the competing maximum of 40 takes at least 45
+    place_proxy_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 42);
+
+    let bid = last_bid(&testkit, &ltx.hash());
+    assert_eq!(bid.owner(), tx_first.pub_key());
+    assert_eq!(bid.amount(), MIN_BID);
+    assert_eq!(get_wallet(&testkit, tx_second.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_proxy_bid_raised_to_buy_now_price() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().buy_now(50));
+    let (tx_proxy, proxy_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    place_proxy_bid(&mut testkit, &tx_proxy.pub_key(), &proxy_key, &ltx.hash(), 80);
+    assert!(get_lot(&testkit, &ltx.hash()).is_open());
+
+    // Outbidding 49 raises the proxy bid to the buy-now price, so the lot is sold at it
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 49);
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+
+    let bid = last_bid(&testkit, &ltx.hash());
+    assert_eq!(bid.owner(), tx_proxy.pub_key());
+    assert_eq!(bid.amount(), 50);
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 50);
+
+    let proxy_wallet = get_wallet(&testkit, tx_proxy.pub_key());
+    assert_eq!(proxy_wallet.available(&native_currency()), BALANCE - 50);
+    assert_eq!(proxy_wallet.frozen(&native_currency()), 0);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_bid_at_buy_now_price_against_earlier_proxy() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().buy_now(50));
+    let (tx_proxy, proxy_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    place_proxy_bid(&mut testkit, &tx_proxy.pub_key(), &proxy_key, &ltx.hash(), 60);
+
+    // The earlier proxy reaches the buy-now price too, so its owner buys the lot
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 55);
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(last_bid(&testkit, &ltx.hash()).owner(), tx_proxy.pub_key());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 50);
+    assert_eq!(get_wallet(&testkit, tx_proxy.pub_key()).available(&native_currency()), BALANCE - 50);
+    assert_eq!(get_wallet(&testkit, tx_proxy.pub_key()).frozen(&native_currency()), 0);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_bid_at_buy_now_price_above_proxy_maximum() {
+    let mut testkit = init_testkit();
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().buy_now(50));
+    let (tx_proxy, proxy_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    place_proxy_bid(&mut testkit, &tx_proxy.pub_key(), &proxy_key, &ltx.hash(), 40);
+
+    // The proxy can't reach the buy-now price, the bidder buys the lot and the proxy is released
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 50);
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(last_bid(&testkit, &ltx.hash()).owner(), tx_bidder.pub_key());
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE - 50);
+    assert_eq!(get_wallet(&testkit, tx_proxy.pub_key()).available(&native_currency()), BALANCE);
+    assert_eq!(get_wallet(&testkit, tx_proxy.pub_key()).frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_competing_proxy_bids_reach_buy_now_price() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().buy_now(50));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    place_proxy_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 60);
+    place_proxy_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 70);
+
+    // The higher maximum wins and pays the buy-now price
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(last_bid(&testkit, &ltx.hash()).owner(), tx_second.pub_key());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 50);
+    assert_eq!(get_wallet(&testkit, tx_first.pub_key()).frozen(&native_currency()), 0);
+    assert_eq!(get_wallet(&testkit, tx_second.pub_key()).available(&native_currency()), BALANCE - 50);
+}
+
+#[test]
+fn test_tx_failed_close_changes_nothing() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new());
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    place_proxy_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx.hash(), 50);
+
+    // Expired lots are closed in `before_commit`, whose changes aren't rolled back on errors
+    let mut fork = testkit.blockchain_mut().fork();
+    let mut schema = Schema::new(&mut fork);
+    schema.wallets_mut().remove(wtx.pub_key());
+    assert!(schema.close_lot(&ltx.hash(), 0).is_err());
+
+    assert!(schema.lot(&ltx.hash()).expect("No lot persisted").is_open());
+    assert!(schema.proxy_bids().get(&ltx.hash()).is_some());
+    let bidder = schema.wallet(tx_bidder.pub_key()).expect("No wallet persisted");
+    assert_eq!(bidder.frozen(&native_currency()), 50);
+    assert_eq!(bidder.available(&native_currency()), BALANCE - 50);
+}
+
+#[test]
+fn test_tx_sealed_bid_auction() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().format(AuctionFormat::SealedBid).end_height(10).reveal_period(3));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+    let (tx_silent, silent_key) = create_wallet(&mut testkit, "Venus", BALANCE);
+    let salt = hash(&[1, 2, 3]);
+
+    commit_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 40, &salt, 50);
+    commit_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 30, &salt, 30);
+    commit_bid(&mut testkit, &tx_silent.pub_key(), &silent_key, &ltx.hash(), 20, &salt, 25);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
+    assert_eq!(get_wallet(&testkit, tx_first.pub_key()).frozen(&native_currency()), 50);
+
+    create_blocks_until(&mut testkit, Height(10));
+    reveal_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 40, &salt);
+    reveal_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 30, &salt);
+    assert!(get_lot(&testkit, &ltx.hash()).is_open());
+
+    // The lot is settled once the reveal phase is over
+    create_blocks_until(&mut testkit, Height(13));
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+
+    let bid = last_bid(&testkit, &ltx.hash());
+    assert_eq!(bid.owner(), tx_first.pub_key());
+    assert_eq!(bid.amount(), 40);
+
+    // The unrevealed deposit is forfeited to the lot owner
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 40 + 25);
+    assert_eq!(get_wallet(&testkit, tx_silent.pub_key()).available(&native_currency()), BALANCE - 25);
+
+    let first_wallet = get_wallet(&testkit, tx_first.pub_key());
+    assert_eq!(first_wallet.available(&native_currency()), BALANCE - 40);
+    assert_eq!(first_wallet.frozen(&native_currency()), 0);
+
+    let second_wallet = get_wallet(&testkit, tx_second.pub_key());
+    assert_eq!(second_wallet.available(&native_currency()), BALANCE);
+    assert_eq!(second_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_sealed_bid_lot_with_excessive_reveal_period() {
+    let mut testkit = init_testkit();
+    let end_height = testkit.height().0 + 10;
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().format(AuctionFormat::SealedBid).end_height(end_height).reveal_period(MAX_REVEAL_PERIOD + 1));
+    assert_eq!(tx_error_code(&testkit, &ltx.hash()), Some(Error::InvalidRevealPeriod as u8));
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().format(AuctionFormat::SealedBid).end_height(u64::max_value()).reveal_period(1));
+    assert_eq!(tx_error_code(&testkit, &ltx.hash()), Some(Error::InvalidRevealPeriod as u8));
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().end_height(end_height).reveal_period(3));
+    assert_eq!(tx_error_code(&testkit, &ltx.hash()), Some(Error::InvalidRevealPeriod as u8));
+
+    assert_eq!(lots_total(&testkit), 0);
+}
+
+#[test]
+fn test_tx_sealed_bid_phases() {
+    let mut testkit = init_testkit();
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().format(AuctionFormat::SealedBid).end_height(8).reveal_period(5));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Venus", BALANCE);
+    let (tx_late, late_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+    let salt = hash(&[1, 2, 3]);
+
+    commit_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 40, &salt, 50);
+    commit_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 30, &salt, 50);
+
+    // Bids can't be revealed during the commit phase
+    reveal_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 30, &salt);
+    assert!(!sealed_bids(&testkit, &ltx.hash())[1].revealed());
+
+    // Open bids aren't accepted on sealed-bid lots
+    let _btx = place_bid(&mut testkit, &tx_late.pub_key(), &late_key, &ltx.hash(), MIN_BID);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
+
+    // Bids can't be committed after the deadline
+    create_blocks_until(&mut testkit, Height(8));
+    commit_bid(&mut testkit, &tx_late.pub_key(), &late_key, &ltx.hash(), 40, &salt, 50);
+    assert_eq!(sealed_bids(&testkit, &ltx.hash()).len(), 2);
+    assert_eq!(get_wallet(&testkit, tx_late.pub_key()).available(&native_currency()), BALANCE);
+
+    // A reveal not matching the commitment is rejected
+    reveal_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 45, &salt);
+    assert!(!sealed_bids(&testkit, &ltx.hash())[0].revealed());
+
+    reveal_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 40, &salt);
+    assert!(sealed_bids(&testkit, &ltx.hash())[0].revealed());
+}
+
+#[test]
+fn test_tx_vickrey_winner_pays_second_price() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().format(AuctionFormat::Vickrey).end_height(10).reveal_period(3));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+    let (tx_third, third_key) = create_wallet(&mut testkit, "Venus", BALANCE);
+    let salt = hash(&[1, 2, 3]);
+
+    commit_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 70, &salt, 80);
+    commit_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 45, &salt, 50);
+    commit_bid(&mut testkit, &tx_third.pub_key(), &third_key, &ltx.hash(), 30, &salt, 30);
+
+    create_blocks_until(&mut testkit, Height(10));
+    reveal_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 70, &salt);
+    reveal_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 45, &salt);
+    reveal_bid(&mut testkit, &tx_third.pub_key(), &third_key, &ltx.hash(), 30, &salt);
+    create_blocks_until(&mut testkit, Height(13));
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    let bid = last_bid(&testkit, &ltx.hash());
+    assert_eq!(bid.owner(), tx_first.pub_key());
+    assert_eq!(bid.amount(), 45);
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 45);
+
+    let first_wallet = get_wallet(&testkit, tx_first.pub_key());
+    assert_eq!(first_wallet.available(&native_currency()), BALANCE - 45);
+    assert_eq!(first_wallet.frozen(&native_currency()), 0);
+    assert_eq!(get_wallet(&testkit, tx_second.pub_key()).available(&native_currency()), BALANCE);
+    assert_eq!(get_wallet(&testkit, tx_third.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_vickrey_single_bid_pays_min_bid() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().format(AuctionFormat::Vickrey).end_height(5).reveal_period(2));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let salt = hash(&[1, 2, 3]);
+
+    commit_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), 60, &salt, 60);
+    create_blocks_until(&mut testkit, Height(5));
+    reveal_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), 60, &salt);
+    create_blocks_until(&mut testkit, Height(7));
+
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(last_bid(&testkit, &ltx.hash()).amount(), MIN_BID);
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + MIN_BID);
+
+    let bidder_wallet = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder_wallet.available(&native_currency()), BALANCE - MIN_BID);
+    assert_eq!(bidder_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_dutch_auction_price() {
+    let mut testkit = init_testkit();
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().dutch(50, 15));
+
+    // The lot has been created at height 2
+    let lot = get_lot(&testkit, &ltx.hash());
+    assert_eq!(lot.current_price(Height(2)), 50);
+    assert_eq!(lot.current_price(Height(3)), 35);
+    assert_eq!(lot.current_price(Height(4)), 20);
+    assert_eq!(lot.current_price(Height(5)), MIN_BID);
+    assert_eq!(lot.current_price(Height(100)), MIN_BID);
+}
+
+#[test]
+fn test_tx_dutch_auction_accept_price() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().dutch(50, 5));
+    let (tx_buyer, buyer_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_late, late_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    // Open bids aren't accepted on Dutch auction lots
+    let _btx = place_bid(&mut testkit, &tx_buyer.pub_key(), &buyer_key, &ltx.hash(), 60);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
+
+    // Accepted at height 6, 4 blocks after the lot has been created
+    create_block_with_transaction(&mut testkit, AcceptPrice::new(&tx_buyer.pub_key(), &ltx.hash(), &buyer_key));
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(last_bid(&testkit, &ltx.hash()).amount(), 30);
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 30);
+
+    let buyer_wallet = get_wallet(&testkit, tx_buyer.pub_key());
+    assert_eq!(buyer_wallet.available(&native_currency()), BALANCE - 30);
+    assert_eq!(buyer_wallet.frozen(&native_currency()), 0);
+
+    create_block_with_transaction(&mut testkit, AcceptPrice::new(&tx_late.pub_key(), &ltx.hash(), &late_key));
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 1);
+    assert_eq!(get_wallet(&testkit, tx_late.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_dutch_auction_with_reserve() {
+    let mut testkit = init_testkit();
+    let _ = create_lot(&mut testkit, LotBuilder::new().dutch(50, 5).reserve(30, &hash(b"salt")));
+
+    assert_eq!(lots_total(&testkit), 0);
+}
+
+#[test]
+fn test_tx_multi_unit_uniform_price() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, owner_key) = create_lot(&mut testkit, LotBuilder::new().quantity(5));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_partial, partial_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+    let (tx_loser, loser_key) = create_wallet(&mut testkit, "Venus", BALANCE);
+
+    place_multi_unit_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 20, 3);
+    place_multi_unit_bid(&mut testkit, &tx_partial.pub_key(), &partial_key, &ltx.hash(), 15, 3);
+    place_multi_unit_bid(&mut testkit, &tx_loser.pub_key(), &loser_key, &ltx.hash(), 12, 2);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 3);
+    assert_eq!(get_wallet(&testkit, tx_first.pub_key()).frozen(&native_currency()), 60);
+    assert_eq!(get_wallet(&testkit, tx_partial.pub_key()).frozen(&native_currency()), 45);
+
+    // Units are allocated 3 + 2, every winner pays the lowest winning unit price
+    close_lot(&mut testkit, &wtx.pub_key(), &owner_key, &ltx.hash());
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 5 * 15);
+
+    let first_wallet = get_wallet(&testkit, tx_first.pub_key());
+    assert_eq!(first_wallet.available(&native_currency()), BALANCE - 3 * 15);
+    assert_eq!(first_wallet.frozen(&native_currency()), 0);
+
+    let partial_wallet = get_wallet(&testkit, tx_partial.pub_key());
+    assert_eq!(partial_wallet.available(&native_currency()), BALANCE - 2 * 15);
+    assert_eq!(partial_wallet.frozen(&native_currency()), 0);
+
+    let loser_wallet = get_wallet(&testkit, tx_loser.pub_key());
+    assert_eq!(loser_wallet.available(&native_currency()), BALANCE);
+    assert_eq!(loser_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_multi_unit_lot_with_escrow() {
+    let mut testkit = init_testkit_with_config(AuctionConfig { escrow_period: ESCROW_PERIOD, ..config() });
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().quantity(5));
+
+    assert!(Schema::new(&testkit.snapshot()).lot(&ltx.hash()).is_none());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_multi_unit_bid_above_lot_quantity() {
+    let mut testkit = init_testkit();
+    let (ltx, _wtx, _) = create_lot(&mut testkit, LotBuilder::new().quantity(5));
+    let (tx_bidder, key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    place_multi_unit_bid(&mut testkit, &tx_bidder.pub_key(), &key, &ltx.hash(), MIN_BID, 6);
+
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_reverse_auction() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, owner_key) = create_lot(&mut testkit, LotBuilder::new().format(AuctionFormat::Reverse).min_bid(60));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    let owner_wallet = get_wallet(&testkit, wtx.pub_key());
+    assert_eq!(owner_wallet.available(&native_currency()), BALANCE - 60);
+    assert_eq!(owner_wallet.frozen(&native_currency()), 60);
+
+    // Bids above the budget or the current lowest bid are rejected
+    let _btx = place_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 61);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
+
+    let _btx = place_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 50);
+    let _btx = place_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 40);
+    let _btx = place_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 45);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 2);
+    assert_eq!(get_wallet(&testkit, tx_second.pub_key()).frozen(&native_currency()), 0);
+
+    close_lot(&mut testkit, &wtx.pub_key(), &owner_key, &ltx.hash());
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+
+    let owner_wallet = get_wallet(&testkit, wtx.pub_key());
+    assert_eq!(owner_wallet.available(&native_currency()), BALANCE - 40);
+    assert_eq!(owner_wallet.frozen(&native_currency()), 0);
+    assert_eq!(get_wallet(&testkit, tx_second.pub_key()).available(&native_currency()), BALANCE + 40);
+    assert_eq!(get_wallet(&testkit, tx_first.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_reverse_auction_with_escrow() {
+    let mut testkit = init_testkit_with_config(AuctionConfig { escrow_period: ESCROW_PERIOD, ..config() });
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().format(AuctionFormat::Reverse).min_bid(60));
+
+    // The budget isn't frozen for a rejected lot
+    assert!(Schema::new(&testkit.snapshot()).lot(&ltx.hash()).is_none());
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_reverse_auction_bid_without_wallet() {
+    let mut testkit = init_testkit();
+    let end_height = testkit.height().0 + 5;
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().format(AuctionFormat::Reverse).min_bid(60).end_height(end_height));
+    let (pub_key, key) = crypto::gen_keypair();
+
+    let _btx = place_bid(&mut testkit, &pub_key, &key, &ltx.hash(), 30);
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 0);
+
+    // The lot closes at its deadline without a sale and the budget is released
+    create_blocks_until(&mut testkit, Height(end_height + 1));
+    assert!(get_lot(&testkit, &ltx.hash()).is_closed());
+    let owner_wallet = get_wallet(&testkit, wtx.pub_key());
+    assert_eq!(owner_wallet.available(&native_currency()), BALANCE);
+    assert_eq!(owner_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_reverse_auction_budget_above_balance() {
+    let mut testkit = init_testkit();
+    let _ = create_lot(&mut testkit, LotBuilder::new().format(AuctionFormat::Reverse).min_bid(BALANCE + 1));
+
+    assert_eq!(lots_total(&testkit), 0);
+}
+
+#[test]
+fn test_tx_retract_bid() {
+    let mut testkit = init_testkit();
+    let (ltx, wtx, _) = create_lot(&mut testkit, LotBuilder::new().retraction(3, 2));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 20);
+    let _btx = place_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 90);
+    let _rtx = retract_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash());
+
+    // The previous bid is the highest one again and its amount is frozen
+    let bid = last_bid(&testkit, &ltx.hash());
+    assert_eq!(bid.owner(), tx_first.pub_key());
+    assert_eq!(bid.amount(), 20);
+    assert_eq!(get_wallet(&testkit, tx_first.pub_key()).frozen(&native_currency()), 20);
+
+    let history = Schema::new(&testkit.snapshot()).bid_history(&ltx.hash()).iter().collect::<Vec<_>>();
+    assert!(!history[0].retracted());
+    assert!(history[1].retracted());
+
+    // The penalty is paid to the lot owner
+    let second_wallet = get_wallet(&testkit, tx_second.pub_key());
+    assert_eq!(second_wallet.available(&native_currency()), BALANCE - 2);
+    assert_eq!(second_wallet.frozen(&native_currency()), 0);
+    assert_eq!(get_wallet(&testkit, wtx.pub_key()).available(&native_currency()), BALANCE + 2);
+}
+
+#[test]
+fn test_tx_retract_bid_lapses_unfunded_bids() {
+    let mut testkit = init_testkit();
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().retraction(3, 0));
+    let (ltx_other, _, _) = create_lot(&mut testkit, LotBuilder::new().name("other"));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 20);
+    let _btx = place_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 30);
+    // The outbid participant spends the released funds elsewhere
+    let _btx = place_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx_other.hash(), 90);
+    let _rtx = retract_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash());
+
+    assert!(Schema::new(&testkit.snapshot()).last_bid(&ltx.hash()).is_none());
+    assert_eq!(bid_history_size(&testkit, &ltx.hash()), 2);
+    assert_eq!(get_wallet(&testkit, tx_first.pub_key()).frozen(&native_currency()), 90);
+
+    // The unfunded bid is told apart from the retracted one
+    let history = Schema::new(&testkit.snapshot()).bid_history(&ltx.hash()).iter().collect::<Vec<_>>();
+    assert!(history[0].lapsed() && !history[0].retracted());
+    assert!(history[1].retracted() && !history[1].lapsed());
+    assert_eq!(get_wallet(&testkit, tx_second.pub_key()).available(&native_currency()), BALANCE);
+}
+
+#[test]
+fn test_tx_retract_bid_skips_own_earlier_bids() {
+    let mut testkit = init_testkit();
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().retraction(5, 2));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 20);
+    let _btx = place_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 30);
+    let _btx = place_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 40);
+    let _rtx = retract_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash());
+
+    // The retracting bidder doesn't stay in the lead with the earlier bid
+    assert_eq!(last_bid(&testkit, &ltx.hash()).owner(), tx_first.pub_key());
+    assert_eq!(get_wallet(&testkit, tx_first.pub_key()).frozen(&native_currency()), 20);
+
+    let history = Schema::new(&testkit.snapshot()).bid_history(&ltx.hash()).iter().collect::<Vec<_>>();
+    assert!(history[0].is_active());
+    assert!(history[1].retracted() && history[2].retracted());
+
+    let second_wallet = get_wallet(&testkit, tx_second.pub_key());
+    assert_eq!(second_wallet.available(&native_currency()), BALANCE - 2);
+    assert_eq!(second_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_retract_bid_outside_window() {
+    let mut testkit = init_testkit();
+    let (ltx, _, _) = create_lot(&mut testkit, LotBuilder::new().retraction(3, 0));
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash(), 20);
+    let _btx = place_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash(), 30);
+
+    // Only the highest bidder can retract
+    let _rtx = retract_bid(&mut testkit, &tx_first.pub_key(), &first_key, &ltx.hash());
+    assert_eq!(last_bid(&testkit, &ltx.hash()).owner(), tx_second.pub_key());
+
+    create_blocks_until(&mut testkit, Height(10));
+    let _rtx = retract_bid(&mut testkit, &tx_second.pub_key(), &second_key, &ltx.hash());
+    assert_eq!(last_bid(&testkit, &ltx.hash()).owner(), tx_second.pub_key());
+    assert_eq!(get_wallet(&testkit, tx_second.pub_key()).frozen(&native_currency()), 30);
+}
+
+#[test]
+fn test_tx_bundle_bid_wins() {
+    let mut testkit = init_testkit();
+    let end_height = 15;
+    let (ltx_a, wtx_a, _) = create_lot(&mut testkit, LotBuilder::new().name("A").end_height(end_height));
+    let (ltx_b, wtx_b, _) = create_lot(&mut testkit, LotBuilder::new().name("B").end_height(end_height));
+    let (ltx_c, wtx_c, _) = create_lot(&mut testkit, LotBuilder::new().name("C").end_height(end_height));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_bundler, bundler_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx_a.hash(), 30);
+    let _ = place_bundle_bid(&mut testkit, &tx_bundler.pub_key(), &bundler_key, &[ltx_a.hash(), ltx_b.hash()], 50);
+    // Overlaps with the first bundle and brings less over the separate bids
+    let _ = place_bundle_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &[ltx_b.hash(), ltx_c.hash()], 25);
+    assert_eq!(get_wallet(&testkit, tx_bundler.pub_key()).frozen(&native_currency()), 50);
+
+    create_blocks_until(&mut testkit, Height(end_height));
+
+    // The bundle amount is split in proportion to the separate prices of 30 and 10
+    assert_eq!(last_bid(&testkit, &ltx_a.hash()).amount(), 37);
+    assert_eq!(last_bid(&testkit, &ltx_b.hash()).amount(), 13);
+    assert_eq!(get_wallet(&testkit, wtx_a.pub_key()).available(&native_currency()), BALANCE + 37);
+    assert_eq!(get_wallet(&testkit, wtx_b.pub_key()).available(&native_currency()), BALANCE + 13);
+    assert_eq!(get_wallet(&testkit, wtx_c.pub_key()).available(&native_currency()), BALANCE);
+    assert!(get_lot(&testkit, &ltx_c.hash()).is_closed());
+
+    let bundler_wallet = get_wallet(&testkit, tx_bundler.pub_key());
+    assert_eq!(bundler_wallet.available(&native_currency()), BALANCE - 50);
+    assert_eq!(bundler_wallet.frozen(&native_currency()), 0);
+
+    let bidder_wallet = get_wallet(&testkit, tx_bidder.pub_key());
+    assert_eq!(bidder_wallet.available(&native_currency()), BALANCE);
+    assert_eq!(bidder_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_failed_bundle_settlement_releases_bundles() {
+    let mut testkit = init_testkit();
+    let end_height = 12;
+    let (ltx_a, _, _) = create_lot(&mut testkit, LotBuilder::new().name("A").end_height(end_height));
+    let (ltx_b, _, _) = create_lot(&mut testkit, LotBuilder::new().name("B").end_height(end_height));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_bundler, bundler_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx_a.hash(), 30);
+    let btx = place_bundle_bid(&mut testkit, &tx_bundler.pub_key(), &bundler_key, &[ltx_a.hash(), ltx_b.hash()], 50);
+    create_blocks_until(&mut testkit, Height(end_height - 1));
+
+    // The winning bundle can't be awarded, since the bid it replaces can't be released
+    let mut fork = testkit.blockchain_mut().fork();
+    let mut schema = Schema::new(&mut fork);
+    schema.wallets_mut().remove(tx_bidder.pub_key());
+    schema.close_expired_lots();
+
+    assert!(schema.bundle_bid(&btx.hash()).is_none());
+    assert_eq!(schema.expiring_bundle_bids(Height(end_height)).len(), 0);
+    let bundler = schema.wallet(tx_bundler.pub_key()).expect("No wallet persisted");
+    assert_eq!(bundler.available(&native_currency()), BALANCE);
+    assert_eq!(bundler.frozen(&native_currency()), 0);
+    assert_eq!(schema.last_bid(&ltx_a.hash()).map(|bid| *bid.owner()), Some(*tx_bidder.pub_key()));
+}
+
+#[test]
+fn test_tx_bundle_bid_loses_to_separate_bids() {
+    let mut testkit = init_testkit();
+    let end_height = 12;
+    let (ltx_a, wtx_a, _) = create_lot(&mut testkit, LotBuilder::new().name("A").end_height(end_height));
+    let (ltx_b, _, _) = create_lot(&mut testkit, LotBuilder::new().name("B").end_height(end_height));
+    let (tx_bidder, bidder_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_bundler, bundler_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+
+    let _ = place_bundle_bid(&mut testkit, &tx_bundler.pub_key(), &bundler_key, &[ltx_a.hash(), ltx_b.hash()], 40);
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx_a.hash(), 30);
+    let _btx = place_bid(&mut testkit, &tx_bidder.pub_key(), &bidder_key, &ltx_b.hash(), 10);
+
+    // Ties are won by the separate bids
+    create_blocks_until(&mut testkit, Height(end_height));
+
+    assert_eq!(last_bid(&testkit, &ltx_a.hash()).owner(), tx_bidder.pub_key());
+    assert_eq!(get_wallet(&testkit, wtx_a.pub_key()).available(&native_currency()), BALANCE + 30);
+    assert_eq!(get_wallet(&testkit, tx_bidder.pub_key()).available(&native_currency()), BALANCE - 40);
+
+    let bundler_wallet = get_wallet(&testkit, tx_bundler.pub_key());
+    assert_eq!(bundler_wallet.available(&native_currency()), BALANCE);
+    assert_eq!(bundler_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_bundle_bid_on_lots_with_different_deadlines() {
+    let mut testkit = init_testkit();
+    let (ltx_a, _, _) = create_lot(&mut testkit, LotBuilder::new().name("A").end_height(10));
+    let (ltx_b, _, _) = create_lot(&mut testkit, LotBuilder::new().name("B").end_height(11));
+    let (tx_bundler, bundler_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+
+    let _ = place_bundle_bid(&mut testkit, &tx_bundler.pub_key(), &bundler_key, &[ltx_a.hash(), ltx_b.hash()], 40);
+
+    let bundler_wallet = get_wallet(&testkit, tx_bundler.pub_key());
+    assert_eq!(bundler_wallet.available(&native_currency()), BALANCE);
+    assert_eq!(bundler_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_order_book_matching() {
+    let mut testkit = init_testkit();
+    let (tx_seller, seller_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+    let atx = create_fungible_asset(&mut testkit, &tx_seller.pub_key(), &seller_key, 100);
+    let asset = atx.hash();
+
+    let otx = place_order(&mut testkit, &tx_first.pub_key(), &first_key, &asset, OrderSide::Buy, 5, 10);
+    let _ = place_order(&mut testkit, &tx_second.pub_key(), &second_key, &asset, OrderSide::Buy, 6, 5);
+    assert_eq!(get_wallet(&testkit, tx_first.pub_key()).frozen(&native_currency()), 50);
+
+    // The better priced buy order is filled first, trades happen at the resting orders' prices
+    let _ = place_order(&mut testkit, &tx_seller.pub_key(), &seller_key, &asset, OrderSide::Sell, 4, 12);
+
+    let trades = trades(&testkit, &asset);
+    assert_eq!(trades.len(), 2);
+    assert_eq!(trades[0].buyer(), tx_second.pub_key());
+    assert_eq!((trades[0].price(), trades[0].quantity()), (6, 5));
+    assert_eq!(trades[1].buyer(), tx_first.pub_key());
+    assert_eq!((trades[1].price(), trades[1].quantity()), (5, 7));
+
+    assert_eq!(get_wallet(&testkit, tx_seller.pub_key()).available(&native_currency()), BALANCE + 30 + 35);
+    assert_eq!(asset_balance(&testkit, &asset, tx_seller.pub_key()), 88);
+    assert_eq!(asset_balance(&testkit, &asset, tx_second.pub_key()), 5);
+    assert_eq!(asset_balance(&testkit, &asset, tx_first.pub_key()), 7);
+
+    let first_wallet = get_wallet(&testkit, tx_first.pub_key());
+    assert_eq!(first_wallet.available(&native_currency()), BALANCE - 50);
+    assert_eq!(first_wallet.frozen(&native_currency()), 15);
+
+    // Cancelling the partially filled order releases the rest of its funds
+    create_block_with_transaction(&mut testkit, CancelOrder::new(&tx_first.pub_key(), &otx.hash(), &first_key));
+    let first_wallet = get_wallet(&testkit, tx_first.pub_key());
+    assert_eq!(first_wallet.available(&native_currency()), BALANCE - 35);
+    assert_eq!(first_wallet.frozen(&native_currency()), 0);
+}
+
+#[test]
+fn test_tx_order_book_time_priority() {
+    let mut testkit = init_testkit();
+    let (tx_seller, seller_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let (tx_first, first_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let (tx_second, second_key) = create_wallet(&mut testkit, "Mars", BALANCE);
+    let atx = create_fungible_asset(&mut testkit, &tx_seller.pub_key(), &seller_key, 100);
+    let asset = atx.hash();
+
+    let _ = place_order(&mut testkit, &tx_first.pub_key(), &first_key, &asset, OrderSide::Buy, 5, 2);
+    let _ = place_order(&mut testkit, &tx_second.pub_key(), &second_key, &asset, OrderSide::Buy, 5, 2);
+    let _ = place_order(&mut testkit, &tx_seller.pub_key(), &seller_key, &asset, OrderSide::Sell, 5, 3);
+
+    assert_eq!(asset_balance(&testkit, &asset, tx_first.pub_key()), 2);
+    assert_eq!(asset_balance(&testkit, &asset, tx_second.pub_key()), 1);
+
+    // Sell orders which don't cross the best bid rest in the book until cancelled
+    let otx = place_order(&mut testkit, &tx_seller.pub_key(), &seller_key, &asset, OrderSide::Sell, 8, 10);
+    assert_eq!(asset_balance(&testkit, &asset, tx_seller.pub_key()), 87);
+    assert_eq!(trades(&testkit, &asset).len(), 2);
+
+    create_block_with_transaction(&mut testkit, CancelOrder::new(&tx_seller.pub_key(), &otx.hash(), &seller_key));
+    assert_eq!(asset_balance(&testkit, &asset, tx_seller.pub_key()), 97);
+}
+
+#[test]
+fn test_tx_sell_order_above_holdings() {
+    let mut testkit = init_testkit();
+    let (tx_seller, seller_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let atx = create_fungible_asset(&mut testkit, &tx_seller.pub_key(), &seller_key, 10);
+
+    let otx = place_order(&mut testkit, &tx_seller.pub_key(), &seller_key, &atx.hash(), OrderSide::Sell, 5, 11);
+
+    assert!(Schema::new(&testkit.snapshot()).order(&otx.hash()).is_none());
+    assert_eq!(asset_balance(&testkit, &atx.hash(), tx_seller.pub_key()), 10);
+}
+
+#[test]
+fn test_tx_asset_ledger_in_state_hash() {
+    let mut testkit = init_testkit();
+    let (tx_seller, seller_key) = create_wallet(&mut testkit, PHOBOS, BALANCE);
+    let (tx_buyer, buyer_key) = create_wallet(&mut testkit, DEIMOS, BALANCE);
+    let atx = create_fungible_asset(&mut testkit, &tx_seller.pub_key(), &seller_key, 100);
+    let asset = atx.hash();
+
+    let _ = place_order(&mut testkit, &tx_buyer.pub_key(), &buyer_key, &asset, OrderSide::Buy, 5, 2);
+    let otx = place_order(&mut testkit, &tx_seller.pub_key(), &seller_key, &asset, OrderSide::Sell, 5, 10);
+    create_block_with_transaction(&mut testkit, CancelOrder::new(&tx_seller.pub_key(), &otx.hash(), &seller_key));
+
+    // The ledger summary follows holdings and trades, including the units returned by the cancellation
+    let snapshot = testkit.snapshot();
+    let schema = Schema::new(&snapshot);
+    let ledger = schema.asset_ledgers().get(&asset).unwrap();
+    assert_eq!(schema.asset_holdings(&asset).get(&tx_seller.pub_key()), Some(98));
+    assert_eq!(*ledger.holdings_hash(), schema.asset_holdings(&asset).merkle_root());
+    assert_eq!(ledger.trades_len(), 1);
+    assert_eq!(*ledger.trades_hash(), schema.trades(&asset).merkle_root());
+
+    let state_hash = schema.state_hash();
+    assert_eq!(state_hash[ASSET_LEDGERS_TABLE_INDEX], schema.asset_ledgers().merkle_root());
+    assert!(state_hash.contains(&schema.orders().merkle_root()));
+    assert!(state_hash.contains(&schema.fungible_assets().merkle_root()));
+}
+
+/// Asserts that funds held by wallets add up to the total issuance of each currency.
+fn assert_supply_invariant(testkit: &TestKit) {
+    let snapshot = testkit.snapshot();
+    let schema = Schema::new(&snapshot);
+    assert!(
+        schema.check_supply_invariant(),
+        "Supply invariant violated: issued and held {:?}",
+        schema
+            .supply()
+            .iter()
+            .map(|(currency, total)| (currency, total, schema.wallets_total(&currency)))
+            .collect::<Vec<_>>()
+    );
+}
+
+/// Creates an empty block and checks the supply invariant.
+fn create_block(testkit: &mut TestKit) {
+    testkit.create_block();
+    assert_supply_invariant(testkit);
+}
+
+/// Creates empty blocks up to the given height, checking the supply invariant after each one.
+fn create_blocks_until(testkit: &mut TestKit, height: Height) {
+    while testkit.height() < height {
+        create_block(testkit);
+    }
+}
+
+/// Creates a block with the given transaction and checks the supply invariant.
+fn create_block_with_transaction<T: Transaction>(testkit: &mut TestKit, tx: T) {
+    testkit.create_block_with_transaction(tx);
+    assert_supply_invariant(testkit);
+}
+
+/// Initializes testkit with `Service`.
+fn init_testkit() -> TestKit {
+    TestKitBuilder::validator()
+        .with_service(service())
+        .create()
+}
+
+/// Initializes testkit with `Service` using the given configuration.
+fn init_testkit_with_config(config: AuctionConfig) -> TestKit {
+    TestKitBuilder::validator()
+        .with_service(Service::new(config))
+        .create()
+}
+
+/// Initializes testkit with `Service` charging the test listing fee and commission.
+fn init_testkit_with_fees() -> TestKit {
+    init_testkit_with_config(fees_config())
+}
+
+/// Returns the test configuration charging the test listing fee and commission.
+fn fees_config() -> AuctionConfig {
+    AuctionConfig {
+        treasury: Some(treasury_keys().0),
+        listing_fee: LISTING_FEE,
+        commission_percent: COMMISSION_PERCENT,
+        ..config()
+    }
+}
+
+/// Replaces the service configuration, creating blocks until the new one is applied.
+fn change_config(testkit: &mut TestKit, config: AuctionConfig) {
+    // The proposal is committed with the next block, which leaves one more block before it is applied
+    let actual_from = Height(testkit.height().0 + 3);
+    let mut proposal = testkit.configuration_change_proposal();
+    proposal.set_service_config(auction::SERVICE_NAME, config);
+    proposal.set_actual_from(actual_from);
+    testkit.commit_configuration_change(proposal);
+    create_blocks_until(testkit, actual_from);
+}
+
+/// Initializes testkit with `Service` and the time oracle using the given time provider.
+fn init_testkit_with_time(provider: &MockTimeProvider) -> TestKit {
+    TestKitBuilder::validator()
+        .with_service(service())
+        .with_service(TimeService::with_provider(provider.clone()))
+        .create()
+}
+
+/// Sets the mocked time and creates blocks until the time oracle reports it as the consensus time.
+fn set_consensus_time(testkit: &mut TestKit, provider: &MockTimeProvider, time: DateTime<Utc>) {
+    provider.set_time(time);
+    // The first block broadcasts the validator's time, the second one commits it
+    let height = testkit.height().next().next();
+    create_blocks_until(testkit, height);
+}
+
+/// Creates a wallet with the given name and a random key and issues the balance to it in the same block.
+fn create_wallet(testkit: &mut TestKit, name: &str, balance: u64) -> (CreateWallet, SecretKey) {
+    let (pubkey, key) = crypto::gen_keypair();
+    let (issuer, issuer_key) = issuer_keys();
+    let tx = CreateWallet::new(&pubkey, name, &key);
+    let txs: Vec<Box<dyn Transaction>> = vec![tx.clone().into(), Issue::new(&issuer, &pubkey, &native_currency(), balance, 0, &issuer_key).into()];
+    testkit.create_block_with_transactions(txs);
+    assert_supply_invariant(testkit);
+    (tx, key)
+}
+
+/// Returns the error code a committed transaction has failed with, `None` if it has succeeded.
+fn tx_error_code(testkit: &TestKit, tx_hash: &Hash) -> Option<u8> {
+    let snapshot = testkit.snapshot();
+    let result = blockchain::Schema::new(&snapshot).transaction_results().get(tx_hash).expect("No transaction result");
+    match result.map_err(|err| err.error_type()) {
+        Err(TransactionErrorType::Code(code)) => Some(code),
+        _ => None,
+    }
+}
+
+/// Returns the wallet identified by the given public key.
+fn get_wallet(testkit: &TestKit, pubkey: &PublicKey) -> Wallet {
+    Schema::new(&testkit.snapshot()).wallet(pubkey).expect("No wallet persisted")
+}
+
+/// Registers a currency on behalf of the test issuer.
+fn register_currency(testkit: &mut TestKit) -> RegisterCurrency {
+    let (issuer, issuer_key) = issuer_keys();
+    let tx = RegisterCurrency::new(&issuer, "gold", 2, testkit.height().0, &issuer_key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+/// Issues funds in the given currency on behalf of the test issuer.
+fn issue(testkit: &mut TestKit, to: &PublicKey, currency: &Hash, amount: u64) -> Issue {
+    let (issuer, issuer_key) = issuer_keys();
+    let tx = Issue::new(&issuer, to, currency, amount, testkit.height().0, &issuer_key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn transfer(testkit: &mut TestKit, from: &PublicKey, key: &SecretKey, to: &PublicKey, amount: u64) -> Transfer {
+    let seed = testkit.height().0;
+    let tx = Transfer::new(from, to, &native_currency(), amount, seed, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+/// Creates a wallet for the lot owner and the lot built by the provided builder.
+fn create_lot(testkit: &mut TestKit, lot: LotBuilder) -> (CreateLot, CreateWallet, SecretKey) {
+    let (tx, key) = create_wallet(testkit, PHOBOS, BALANCE);
+    let ltx = create_lot_by(testkit, tx.pub_key(), &key, lot);
+    (ltx, tx, key)
+}
+
+fn issue_asset(testkit: &mut TestKit, owner: &PublicKey, key: &SecretKey) -> IssueAsset {
+    let tx = IssueAsset::new(owner, "painting", testkit.height().0, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn get_asset(testkit: &TestKit, id: &Hash) -> Asset {
+    Schema::new(&testkit.snapshot()).asset(id).expect("No asset persisted")
+}
+
+/// Creates the lot built by the provided builder on behalf of an existing wallet.
+fn create_lot_by(testkit: &mut TestKit, owner: &PublicKey, key: &SecretKey, lot: LotBuilder) -> CreateLot {
+    let tx = lot.sign(owner, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn create_fungible_asset(testkit: &mut TestKit, issuer: &PublicKey, key: &SecretKey, supply: u64) -> CreateFungibleAsset {
+    let tx = CreateFungibleAsset::new(issuer, "test asset", supply, 0, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn place_order(
+    testkit: &mut TestKit,
+    owner: &PublicKey,
+    key: &SecretKey,
+    asset: &Hash,
+    side: OrderSide,
+    price: u64,
+    quantity: u64,
+) -> PlaceOrder {
+    let seed = testkit.height().0;
+    let tx = PlaceOrder::new(owner, asset, side as u8, price, quantity, seed, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn asset_balance(testkit: &TestKit, asset: &Hash, owner: &PublicKey) -> u64 {
+    Schema::new(&testkit.snapshot()).asset_balance(asset, owner)
+}
+
+fn trades(testkit: &TestKit, asset: &Hash) -> Vec<Trade> {
+    Schema::new(&testkit.snapshot()).trades(asset).iter().collect()
+}
+
+fn get_lot(testkit: &TestKit, id: &Hash) -> Lot {
+    Schema::new(&testkit.snapshot()).lot(id).expect("No lot persisted")
+}
+
+fn place_bid(testkit: &mut TestKit, bidder: &PublicKey, key: &SecretKey, lot_id: &Hash, bid: u64) -> PlaceBid {
+    place_multi_unit_bid(testkit, bidder, key, lot_id, bid, 1)
+}
+
+fn place_multi_unit_bid(testkit: &mut TestKit, bidder: &PublicKey, key: &SecretKey, lot_id: &Hash, bid: u64, quantity: u64) -> PlaceBid {
+    let tx = PlaceBid::new(&bidder, lot_id, bid, quantity, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn place_proxy_bid(testkit: &mut TestKit, bidder: &PublicKey, key: &SecretKey, lot_id: &Hash, max_amount: u64) -> PlaceProxyBid {
+    let tx = PlaceProxyBid::new(bidder, lot_id, max_amount, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn place_bundle_bid(testkit: &mut TestKit, bidder: &PublicKey, key: &SecretKey, lots: &[Hash], amount: u64) -> PlaceBundleBid {
+    let seed = testkit.height().0;
+    let tx = PlaceBundleBid::new(bidder, lots, amount, seed, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn retract_bid(testkit: &mut TestKit, bidder: &PublicKey, key: &SecretKey, lot_id: &Hash) -> RetractBid {
+    let seed = testkit.height().0;
+    let tx = RetractBid::new(bidder, lot_id, seed, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn commit_bid(testkit: &mut TestKit, bidder: &PublicKey, key: &SecretKey, lot_id: &Hash, amount: u64, salt: &Hash, deposit: u64) -> CommitBid {
+    let tx = CommitBid::new(bidder, lot_id, &amount_commitment(amount, salt), deposit, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn reveal_bid(testkit: &mut TestKit, bidder: &PublicKey, key: &SecretKey, lot_id: &Hash, amount: u64, salt: &Hash) -> RevealBid {
+    let tx = RevealBid::new(bidder, lot_id, amount, salt, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn close_lot(testkit: &mut TestKit, owner: &PublicKey, key: &SecretKey, lot_id: &Hash) -> CloseLot {
+    close_lot_with_reserve(testkit, owner, key, lot_id, 0, &Hash::zero())
+}
+
+fn close_lot_with_reserve(testkit: &mut TestKit, owner: &PublicKey, key: &SecretKey, lot_id: &Hash, reserve: u64, salt: &Hash) -> CloseLot {
+    let tx = CloseLot::new(owner, lot_id, reserve, salt, key);
+    create_block_with_transaction(testkit, tx.clone());
+    tx
+}
+
+fn last_bid(testkit: &TestKit, lot_id: &Hash) -> Bid {
+    Schema::new(&testkit.snapshot()).last_bid(lot_id).expect("No bid history for lot")
+}
+
+fn extensions(testkit: &TestKit, lot_id: &Hash) -> Vec<Extension> {
+    Schema::new(&testkit.snapshot()).extensions(lot_id).iter().collect()
+}
+
+fn sealed_bids(testkit: &TestKit, lot_id: &Hash) -> Vec<SealedBid> {
+    Schema::new(&testkit.snapshot()).sealed_bids(lot_id).iter().collect()
 }
 
 fn bid_history_size(testkit: &TestKit, lot_id: &Hash) -> u64 {